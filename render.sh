@@ -9,7 +9,7 @@ if [ -f $build_path/im.ppm ]; then
 else
   echo "No previous render found"
 fi
-# Render and output .ppm image file
-$build_path/rtiow >> $build_path/im.ppm
+# Render the scene named by the first argument, if any, and output .ppm image file
+$build_path/rtiow "$@" >> $build_path/im.ppm
 # Show rendered image
 open $build_path/im.ppm
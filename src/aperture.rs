@@ -0,0 +1,68 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+use crate::color::luminance;
+use crate::rt_weekend::{degrees_to_radians, random_f64};
+use crate::sampling::Distribution2D;
+use crate::texture::Texture;
+use crate::vec3::{random_in_unit_disk, Point3};
+
+/// Texels across each side of the grid an aperture image is sampled on
+const IMAGE_RESOLUTION: usize = 256;
+
+/// Shape of the lens opening, which out-of-focus highlights take on
+pub enum Aperture {
+    Circle,
+    /// regular polygon of diaphragm [blades], turned by [rotation] degrees
+    Polygon { blades: u32, rotation: f64 },
+    /// opening as bright as the luminance of a texture over the unit square,
+    /// like a heart or a star cut into a lens cap. See [Aperture::image]
+    Image(Distribution2D),
+}
+
+impl Aperture {
+    /// Opening shaped like [texture], tabulated once so that sampling it
+    /// needs no retries. Panics if the texture lets no light through
+    pub fn image(texture: Arc<dyn Texture>) -> Self {
+        let mut weights = Vec::with_capacity(IMAGE_RESOLUTION * IMAGE_RESOLUTION);
+        for j in 0..IMAGE_RESOLUTION {
+            let v = (j as f64 + 0.5) / IMAGE_RESOLUTION as f64;
+
+            for i in 0..IMAGE_RESOLUTION {
+                let u = (i as f64 + 0.5) / IMAGE_RESOLUTION as f64;
+                weights.push(luminance(texture.value(u, v, &Point3::zero())).clamp(0.0, 1.0));
+            }
+        }
+        assert!(weights.iter().any(|&w| w > 0.0), "aperture image lets no light through");
+
+        Aperture::Image(Distribution2D::new(&weights, IMAGE_RESOLUTION, IMAGE_RESOLUTION))
+    }
+
+    /// Uniform point on the opening, within the square from -1 to 1
+    pub fn sample(&self) -> (f64, f64) {
+        match self {
+            Aperture::Circle => {
+                let p = random_in_unit_disk();
+                (p.x(), p.y())
+            }
+            Aperture::Polygon { blades, rotation } => {
+                // a triangle between the center and one of the edges
+                let blades = (*blades).max(3) as f64;
+                let edge = (random_f64() * blades).floor();
+                let angle = |k: f64| degrees_to_radians(*rotation) + 2.0 * PI * k / blades;
+                let (a0, a1) = (angle(edge), angle(edge + 1.0));
+
+                // uniform in the triangle
+                let s = random_f64().sqrt();
+                let t = random_f64();
+                let (b0, b1) = (s * (1.0 - t), s * t);
+
+                (b0 * a0.cos() + b1 * a1.cos(), b0 * a0.sin() + b1 * a1.sin())
+            }
+            Aperture::Image(distribution) => {
+                // in proportion to the transmission
+                let ((u, v), _) = distribution.sample(random_f64(), random_f64());
+                (2.0 * u - 1.0, 2.0 * v - 1.0)
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::color::Color;
+use crate::vec3::{unit_vector, Vec3};
+
+/// What a ray sees when it leaves the scene without hitting anything
+pub trait Background: Send + Sync {
+    /// Radiance arriving from `direction`
+    fn value(&self, direction: Vec3) -> Color;
+
+    /// Picks a unit direction toward the background for next-event estimation,
+    /// together with its solid angle density.
+    /// NB: `None` means the background can't be importance sampled
+    fn sample(&self) -> Option<(Vec3, f64)> {
+        None
+    }
+
+    /// Solid angle density with which `sample` picks `direction`
+    fn pdf(&self, _direction: Vec3) -> f64 {
+        0.0
+    }
+}
+
+/// Vertical blend between two colors, from straight down to straight up
+pub struct Gradient {
+    bottom: Color,
+    top: Color,
+}
+
+impl Default for Gradient {
+    /// White horizon fading into light blue
+    fn default() -> Self {
+        Self::new(Color::new(1.0, 1.0, 1.0), Color::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Gradient {
+    pub fn new(bottom: Color, top: Color) -> Self {
+        Self { bottom, top }
+    }
+}
+
+impl Background for Gradient {
+    fn value(&self, direction: Vec3) -> Color {
+        let unit_direction = unit_vector(direction);
+        let a = 0.5 * (unit_direction.y() + 1.0);
+
+        (1.0 - a) * self.bottom + a * self.top
+    }
+}
@@ -1,10 +1,128 @@
+use std::f64::consts::PI;
 use std::io::stdout;
+use std::sync::Arc;
+use crate::aperture::Aperture;
+use crate::background::{Background, Gradient};
 use crate::color::{write_color, Color};
 use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
+use crate::lens_system::LensSystem;
+use crate::light::{LightList, LightSample};
 use crate::ray::Ray;
 use crate::rt_weekend::{degrees_to_radians, random_f64};
-use crate::vec3::{cross, random_in_unit_disk, random_on_hemisphere, random_unit_vector, unit_vector, Point3, Vec3};
+use crate::sampling::power_heuristic;
+use crate::spectrum::{along_ray, Wavelengths};
+use crate::vec3::{cross, dot, unit_vector, Point3, Vec3};
+
+/// Camera rays averaged when autofocusing
+const AUTOFOCUS_SAMPLES: usize = 16;
+
+/// How rays leave the camera
+#[derive(Copy, Clone)]
+pub enum Projection {
+    /// rays fan out from [lookfrom] over [vfov]
+    Perspective,
+    /// parallel rays along the view direction, over a view [width] in world units
+    Orthographic { width: f64 },
+    /// every direction around [lookfrom]: longitude across the image and
+    /// latitude down it, best with an aspect ratio of 2
+    Equirectangular,
+    /// a circle filling the shorter side of the image, [fov] degrees across
+    Fisheye { mapping: FisheyeMapping, fov: f64 },
+}
+
+/// How the distance from the center of a fisheye image grows with the angle
+/// from the view direction
+#[derive(Copy, Clone)]
+pub enum FisheyeMapping {
+    /// proportional to the angle
+    Equidistant,
+    /// keeps areas, like most real fisheye lenses
+    Equisolid,
+}
+
+/// Where the two views of a stereo image go
+#[derive(Copy, Clone)]
+pub enum StereoLayout {
+    /// left eye on the left, right eye on the right
+    SideBySide,
+    /// left eye on top, right eye below
+    OverUnder,
+}
+
+/// Renders a view for each eye into one image
+#[derive(Copy, Clone)]
+pub struct Stereo {
+    pub layout: StereoLayout,
+    pub interpupillary_distance: f64, // distance between the eyes, in world units
+    pub convergence_distance: f64, // distance from lookfrom where perspective views line up
+}
+
+impl Stereo {
+    pub fn new(layout: StereoLayout, interpupillary_distance: f64, convergence_distance: f64) -> Self {
+        Self { layout, interpupillary_distance, convergence_distance }
+    }
+}
+
+/// Settings of a real camera, which [vfov] and [defocus_angle] follow from,
+/// and [focus_dist] too once a magnification is set. Otherwise the focus
+/// distance stays the camera's own; [autofocus] overrides either
+#[derive(Copy, Clone)]
+pub struct LensSettings {
+    pub focal_length: f64, // in millimeters
+    pub f_stop: f64, // focal length over the diameter of the aperture
+    pub sensor_width: f64, // in millimeters
+    pub sensor_height: f64, // in millimeters
+    pub scale: f64, // world units per millimeter
+    pub magnification: Option<f64>, // size on the sensor over size in focus, which sets focus_dist
+}
+
+impl LensSettings {
+    /// On a full frame sensor, in a world measured in meters
+    pub fn new(focal_length: f64, f_stop: f64) -> Self {
+        Self { focal_length, f_stop, sensor_width: 36.0, sensor_height: 24.0, scale: 0.001, magnification: None }
+    }
+
+    pub fn with_sensor(mut self, width: f64, height: f64) -> Self {
+        self.sensor_width = width;
+        self.sensor_height = height;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Focuses where subjects show at [magnification] times their size on
+    /// the sensor, like 1 for a macro lens at its closest
+    pub fn with_magnification(mut self, magnification: f64) -> Self {
+        self.magnification = Some(magnification);
+        self
+    }
+
+    /// Distance in front of the lens that the magnification puts in focus,
+    /// after the thin lens equation, if there is one
+    pub fn focus_dist(&self) -> Option<f64> {
+        self.magnification.map(|m| self.scale * self.focal_length * (1.0 + 1.0 / m.max(1e-6)))
+    }
+
+    /// Vertical view angle in degrees, for an image [image_ratio] wide per
+    /// unit of height that fills the sensor in one direction
+    pub fn vfov(&self, image_ratio: f64) -> f64 {
+        let height = if image_ratio >= self.sensor_width / self.sensor_height {
+            self.sensor_width / image_ratio
+        } else { self.sensor_height };
+
+        2.0 * (height / (2.0 * self.focal_length)).atan().to_degrees()
+    }
+
+    /// Defocus angle in degrees, for the aperture seen from [focus_dist]
+    pub fn defocus_angle(&self, focus_dist: f64) -> f64 {
+        let aperture_radius = self.scale * self.focal_length / (2.0 * self.f_stop);
+        2.0 * (aperture_radius / focus_dist).atan().to_degrees()
+    }
+}
 
 pub struct Camera {
     // public
@@ -15,7 +133,8 @@ pub struct Camera {
     /// count of random samples for each pixel
     pub max_depth: i32,
 
-    pub vfov: i32, // vertical view angle (field of view) in degrees
+    pub vfov: f64, // vertical view angle (field of view) in degrees
+    pub projection: Projection,
     /// camera basis vectors
     pub lookfrom: Point3,
     pub lookat: Point3,
@@ -23,6 +142,20 @@ pub struct Camera {
     //
     pub defocus_angle: f64, // variation angle of rays through each pixel
     pub focus_dist: f64, // distance from camera lookfrom point to plane of perfect focus
+    pub aperture: Aperture, // shape of the defocus disk
+    pub anamorphic_squeeze: f64, // how much narrower the aperture is than tall, positive
+    pub lens_system: Option<LensSystem>, // real lens in place of vfov and the defocus disk
+    pub lens_settings: Option<LensSettings>, // sets vfov and defocus_angle
+    pub autofocus: Option<(i32, i32)>, // pixel whose first hit sets focus_dist
+    //
+    pub shift_x: f64, // lens shift to the right, in viewport widths, keeping the view direction
+    pub shift_y: f64, // lens shift up, in viewport heights, keeping the view direction
+    pub focus_tilt: f64, // degrees the plane of focus leans back, farther at the top
+    pub focus_swing: f64, // degrees the plane of focus turns, farther on the right
+    //
+    pub background: Arc<dyn Background>, // radiance of rays that escape the scene
+    pub spectral: bool, // trace sampled wavelengths instead of RGB
+    pub stereo: Option<Stereo>, // eyes side by side or over each other, each image_width wide
     //
     image_height: i32, // rendered image height
     center: Point3, // camera center
@@ -36,23 +169,39 @@ pub struct Camera {
     w: Vec3,
     defocus_disk_u: Vec3, // defocus disk horizontal radius
     defocus_disk_v: Vec3, // defocus disk vertical radius
+    focus_normal: Vec3, // normal of the plane of focus
 }
 
-impl Camera {
-    pub fn default() -> Self {
+impl Default for Camera {
+    fn default() -> Self {
         Self {
             aspect_ratio: 1.0,
             image_width: 100,
             samples_per_pixel: 10,
             max_depth: 10,
 
-            vfov: 90,
+            vfov: 90.0,
+            projection: Projection::Perspective,
             lookfrom: Point3::new(0.0, 0.0, 0.0),
             lookat: Point3::new(0.0, 0.0, -1.0),
             vup: Vec3::new(0.0, 1.0, 0.0),
             //
             defocus_angle: 0.0,
             focus_dist: 10.0,
+            aperture: Aperture::Circle,
+            anamorphic_squeeze: 1.0,
+            lens_system: None,
+            lens_settings: None,
+            autofocus: None,
+            //
+            shift_x: 0.0,
+            shift_y: 0.0,
+            focus_tilt: 0.0,
+            focus_swing: 0.0,
+            //
+            background: Arc::new(Gradient::default()),
+            spectral: false,
+            stereo: None,
             //
             image_height: 0,
             center: Point3::zero(),
@@ -65,29 +214,63 @@ impl Camera {
             w: Vec3::zero(),
             defocus_disk_u: Vec3::zero(),
             defocus_disk_v: Vec3::zero(),
+            focus_normal: Vec3::zero(),
         }
     }
+}
 
-    pub fn render(&mut self, world: &dyn Hittable) {
+impl Camera {
+    pub fn render(&mut self, world: &dyn Hittable, lights: &LightList) {
         self.initialize();
 
+        if let Some((i, j)) = self.autofocus {
+            if let Some(focus_dist) = self.focus_dist_at(i, j, world) {
+                self.focus_dist = focus_dist;
+                self.initialize();
+            }
+        }
+
         let mut stdout = stdout();
 
-        let image_width = self.image_width;
-        let image_height = self.image_height;
+        // the combined image holds the views of all eyes
+        let (eyes_across, eyes_down) = match self.stereo {
+            Some(Stereo { layout: StereoLayout::SideBySide, .. }) => (2, 1),
+            Some(Stereo { layout: StereoLayout::OverUnder, .. }) => (1, 2),
+            None => (1, 1),
+        };
+        let image_width = self.image_width * eyes_across;
+        let image_height = self.image_height * eyes_down;
 
         // Render
         println!("P3\n{} {}\n255", image_width, image_height);
 
-        for j in 0..image_height {
-            eprintln!("\rScan lines remaining: {}", image_height - j);
+        for row in 0..image_height {
+            eprintln!("\rScan lines remaining: {}", image_height - row);
 
-            for i in 0..image_width {
+            for column in 0..image_width {
                 let mut pixel_color = Color::new(0.0, 0.0, 0.0);
 
+                // pixel in the view of the eye, the left one first
+                let (i, j) = (column % self.image_width, row % self.image_height);
+                let eye = match self.stereo {
+                    Some(_) if column < self.image_width && row < self.image_height => -1.0,
+                    Some(_) => 1.0,
+                    None => 0.0,
+                };
+
                 for _ in 0..self.samples_per_pixel {
-                    let r = self.get_ray(i, j);
-                    pixel_color += self.ray_color(&r, self.max_depth, world);
+                    let wavelengths = if self.spectral { Some(Wavelengths::sample()) } else { None };
+                    // pixels outside a fisheye circle stay black
+                    let (r, weight) = match self.get_ray(i, j, eye) {
+                        Some((r, weight)) => (r.with_wavelengths(wavelengths), weight),
+                        None => continue,
+                    };
+                    let radiance = self.ray_color(&r, self.max_depth, world, lights, 0.0);
+
+                    pixel_color += weight * match wavelengths {
+                        Some(wavelengths) => wavelengths.to_rgb(radiance),
+                        None => radiance,
+                    };
                 }
 
                 write_color(&mut stdout, &(self.pixel_samples_scale * pixel_color));
@@ -104,14 +287,32 @@ impl Camera {
 
         self.pixel_samples_scale = 1.0 / (self.samples_per_pixel as f64);
 
+        assert!(self.anamorphic_squeeze > 0.0, "anamorphic squeeze must be positive, got {}", self.anamorphic_squeeze);
+
         self.center = self.lookfrom;
 
+        if let Some(lens_settings) = self.lens_settings {
+            // autofocus sets its own focus distance after this
+            if let (Some(focus_dist), None) = (lens_settings.focus_dist(), self.autofocus) {
+                self.focus_dist = focus_dist;
+            }
+            self.vfov = lens_settings.vfov((self.image_width as f64) / (self.image_height as f64));
+            self.defocus_angle = lens_settings.defocus_angle(self.focus_dist);
+        }
+
         // viewport dimensions
         //let focal_length = (self.lookfrom - self.lookat).length();
-        let theta = degrees_to_radians(self.vfov as f64);
-        let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * self.focus_dist;
-        let viewport_width = viewport_height * ((self.image_width as f64) / (self.image_height as f64));
+        let image_ratio = (self.image_width as f64) / (self.image_height as f64);
+        let (viewport_width, viewport_height) = match self.projection {
+            Projection::Orthographic { width } => (width, width / image_ratio),
+            // panoramas don't go through the viewport
+            _ => {
+                let theta = degrees_to_radians(self.vfov);
+                let h = (theta / 2.0).tan();
+                let viewport_height = 2.0 * h * self.focus_dist;
+                (viewport_height * image_ratio, viewport_height)
+            }
+        };
 
         self.w = unit_vector(self.lookfrom - self.lookat);
         self.u = unit_vector(cross(self.vup, self.w));
@@ -125,19 +326,61 @@ impl Camera {
         self.pixel_delta_u = viewport_u / (self.image_width as f64);
         self.pixel_delta_v = viewport_v / (self.image_height as f64);
 
-        // location of upper left pixel
+        // location of upper left pixel. An orthographic viewport goes through
+        // the camera center, where its rays start
+        let viewport_distance = match self.projection {
+            Projection::Orthographic { .. } => 0.0,
+            _ => self.focus_dist,
+        };
         let viewport_upper_left = self.center
-            - (self.focus_dist * self.w)
+            - (viewport_distance * self.w)
             - (viewport_u / 2.0)
-            - (viewport_v / 2.0);
+            - (viewport_v / 2.0)
+            + (self.shift_x * viewport_u)
+            - (self.shift_y * viewport_v);
         self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
 
         let defocus_radius = self.focus_dist * degrees_to_radians(self.defocus_angle / 2.0).tan();
         self.defocus_disk_u = self.u * defocus_radius;
         self.defocus_disk_v = self.v * defocus_radius;
+
+        // the plane of focus gets farther by the tangent of the tilt per unit
+        // up, and of the swing per unit to the right
+        let tilt = degrees_to_radians(self.focus_tilt).tan();
+        let swing = degrees_to_radians(self.focus_swing).tan();
+        self.focus_normal = unit_vector(self.w + tilt * self.v + swing * self.u);
+
+        if let Some(lens_system) = &mut self.lens_system {
+            lens_system.focus(self.focus_dist);
+        }
     }
 
-    fn ray_color(&self, r: &Ray, depth: i32, world: &(dyn Hittable)) -> Color {
+    /// Focus distance that puts the first thing pixel (i,j) sees on the
+    /// plane of focus, tilted or not, if it sees anything. Averaged over
+    /// camera rays, which spread across the pixel and the aperture
+    fn focus_dist_at(&self, i: i32, j: i32, world: &dyn Hittable) -> Option<f64> {
+        let depths: Vec<f64> = (0..AUTOFOCUS_SAMPLES)
+            .filter_map(|_| self.get_ray(i, j, 0.0))
+            .filter_map(|(r, _)| {
+                let mut rec = HitRecord::default();
+                if world.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+                    // the plane of focus through the hit crosses the view
+                    // direction this far from the center
+                    Some(dot(rec.p - self.center, -self.focus_normal) / dot(self.w, self.focus_normal))
+                } else { None }
+            })
+            .collect();
+
+        if depths.is_empty() {
+            return None;
+        }
+
+        Some(depths.iter().sum::<f64>() / depths.len() as f64)
+    }
+
+    /// [scatter_pdf] is the density with which the previous bounce picked
+    /// the direction of [r], or zero for camera rays and specular bounces
+    fn ray_color(&self, r: &Ray, depth: i32, world: &dyn Hittable, lights: &LightList, scatter_pdf: f64) -> Color {
         // final case
         if depth <= 0 {
             return Color::new(0.0, 0.0, 0.0);
@@ -147,47 +390,313 @@ impl Camera {
 
         // recursive case
         if world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            let mut color = along_ray(r, rec.mat.emitted(r, &rec));
+
+            // the emitter may also have been reached by sampling it as a light
+            if scatter_pdf > 0.0 && !color.near_zero() {
+                color *= power_heuristic(scatter_pdf, lights.pdf(r, &rec));
+            }
+
             let mut scattered = Ray::default();
             let mut attenuation = Color::zero();
 
             if rec.mat.scatter(r, &rec, &mut attenuation, &mut scattered) {
-                return attenuation * self.ray_color(&scattered, depth - 1, world);
+                // keeps the path's wavelengths, unless the material changed them
+                if scattered.wavelengths().is_none() && scattered.channel().is_none() {
+                    scattered = scattered.with_wavelengths(r.wavelengths()).with_channel(r.channel());
+                }
+
+                color += self.sample_background(r, &rec, world);
+                color += self.sample_lights(r, &rec, world, lights);
+                let pdf = rec.mat.pdf(r, &rec, *scattered.direction());
+
+                return color + attenuation * self.ray_color(&scattered, depth - 1, world, lights, pdf);
             }
 
-            return Color::new(0.0, 0.0, 0.0);
+            return color;
         }
 
         // no hits
-        let unit_direction = unit_vector(*r.direction());
-        let a = 0.5 * (unit_direction.y() + 1.0);
+        let radiance = along_ray(r, self.background.value(*r.direction()));
+
+        if scatter_pdf <= 0.0 {
+            return radiance;
+        }
+
+        // the background may also have been reached by sampling it directly
+        radiance * power_heuristic(scatter_pdf, self.background.pdf(*r.direction()))
+    }
 
-        (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.5, 0.7, 1.0)
+    /// Next-event estimation: light arriving at the hit point straight
+    /// from a sampled direction of the background
+    fn sample_background(&self, r: &Ray, rec: &HitRecord, world: &dyn Hittable) -> Color {
+        let (direction, pdf) = match self.background.sample() {
+            Some(sample) => sample,
+            None => return Color::zero(),
+        };
+
+        let light = LightSample {
+            direction,
+            distance: f64::INFINITY,
+            radiance: self.background.value(direction),
+            pdf,
+        };
+
+        self.direct_light(r, rec, world, &light, false)
+    }
+
+    /// Next-event estimation: light arriving at the hit point straight
+    /// from one light picked at random
+    fn sample_lights(&self, r: &Ray, rec: &HitRecord, world: &dyn Hittable, lights: &LightList) -> Color {
+        let (light, probability) = match lights.choose() {
+            Some(choice) => choice,
+            None => return Color::zero(),
+        };
+
+        match light.sample(rec.p, r.time()) {
+            Some(mut sample) => {
+                sample.pdf *= probability;
+                self.direct_light(r, rec, world, &sample, light.is_delta())
+            }
+            None => Color::zero(),
+        }
+    }
+
+    /// Contribution of a light sample to the hit point, if nothing blocks
+    /// the way to it
+    fn direct_light(&self, r: &Ray, rec: &HitRecord, world: &dyn Hittable, light: &LightSample, is_delta: bool) -> Color {
+        let f = rec.mat.eval(r, rec, light.direction);
+        if light.pdf <= 0.0 || f.near_zero() {
+            return Color::zero();
+        }
+
+        // scattering never reaches delta lights, so their samples take the full
+        // weight. Otherwise, materials that can't report a density only gather
+        // light by scattering
+        let weight = if is_delta {
+            1.0
+        } else {
+            let scatter_pdf = rec.mat.pdf(r, rec, light.direction);
+            if scatter_pdf <= 0.0 {
+                return Color::zero();
+            }
+            power_heuristic(light.pdf, scatter_pdf)
+        };
+
+        let shadow_ray = Ray::timed(rec.p, light.direction, r.time());
+        if world.hit(&shadow_ray, Interval::new(0.001, light.distance - 0.001), &mut HitRecord::default()) {
+            return Color::zero();
+        }
+
+        f * along_ray(r, light.radiance) * (weight / light.pdf)
     }
 
     /// Construct a camera ray originating from origin and directed
-    /// at randomly sampled point around pixel location (i,j)
-    fn get_ray(&self, i: i32, j: i32) -> Ray {
+    /// at randomly sampled point around pixel location (i,j), if any,
+    /// with the weight of its contribution to the pixel.
+    /// [eye] is -1 for the left eye of a stereo pair, 1 for the right and
+    /// 0 without stereo
+    fn get_ray(&self, i: i32, j: i32, eye: f64) -> Option<(Ray, f64)> {
         let offset = self.sample_square();
         let pixel_sample = self.pixel00_loc
             + (((i as f64) + offset.x()) * self.pixel_delta_u)
             + (((j as f64) + offset.y()) * self.pixel_delta_v);
 
-        let ray_origin = if self.defocus_angle <= 0.0 {
-            self.center
-        } else { self.defocus_disk_sample() };
-        let ray_direction = pixel_sample - ray_origin;
         let ray_time = random_f64();
 
-        Ray::timed(ray_origin, ray_direction, ray_time)
+        // how far the eye sits to the right of the center
+        let shift = match self.stereo {
+            Some(stereo) => eye * stereo.interpupillary_distance / 2.0,
+            None => 0.0,
+        };
+        let eye_center = self.center + shift * self.u;
+
+        match self.projection {
+            Projection::Perspective if self.lens_system.is_some() => {
+                let s = ((i as f64) + 0.5 + offset.x()) / (self.image_width as f64);
+                let t = ((j as f64) + 0.5 + offset.y()) / (self.image_height as f64);
+                let image_ratio = (self.image_width as f64) / (self.image_height as f64);
+
+                let lens_system = self.lens_system.as_ref()?;
+                let (local, weight) = lens_system.sample_ray(s, t, image_ratio)?;
+
+                // from the camera frame of the lens to the world
+                let to_world = |a: Vec3| a.x() * self.u + a.y() * self.v + a.z() * self.w;
+                let origin = eye_center + to_world(*local.origin());
+
+                Some((Ray::timed(origin, to_world(*local.direction()), ray_time), weight))
+            }
+            Projection::Perspective => {
+                // both eyes see the pixel at the same place on the convergence
+                // plane, then focus where that line meets the plane of focus
+                let convergence = self.stereo.map_or(self.focus_dist, |stereo| stereo.convergence_distance);
+                let converged = self.center + (convergence / self.focus_dist) * (pixel_sample - self.center);
+                let sight = converged - eye_center;
+
+                let focus_center = self.center - self.focus_dist * self.w;
+                let distance = dot(focus_center - eye_center, self.focus_normal) / dot(sight, self.focus_normal);
+
+                let ray_origin = if self.defocus_angle <= 0.0 {
+                    eye_center
+                } else { self.defocus_disk_sample() + shift * self.u };
+
+                // a plane of focus tilted past the line of sight focuses it at infinity
+                let ray_direction = if distance > 0.0 && distance.is_finite() {
+                    eye_center + distance * sight - ray_origin
+                } else { sight };
+
+                Some((Ray::timed(ray_origin, ray_direction, ray_time), 1.0))
+            }
+            // orthographic views have no depth of field
+            Projection::Orthographic { .. } => Some((Ray::timed(pixel_sample + shift * self.u, -self.w, ray_time), 1.0)),
+            // panoramas are pinholes, with the image wrapped around the center
+            Projection::Equirectangular | Projection::Fisheye { .. } => {
+                let s = ((i as f64) + 0.5 + offset.x()) / (self.image_width as f64);
+                let t = ((j as f64) + 0.5 + offset.y()) / (self.image_height as f64);
+
+                let direction = self.panoramic_direction(s, t)?;
+
+                // omni-directional stereo: the eyes turn with the view around the
+                // center, and come together toward the poles
+                let origin = match self.projection {
+                    Projection::Equirectangular => self.center + shift * cross(direction, self.v),
+                    _ => eye_center,
+                };
+
+                Some((Ray::timed(origin, direction, ray_time), 1.0))
+            }
+        }
+    }
+
+    /// View direction through the image position [s], [t], both from 0 to 1
+    /// starting at the upper left corner, or none outside of a fisheye circle
+    fn panoramic_direction(&self, s: f64, t: f64) -> Option<Vec3> {
+        match self.projection {
+            Projection::Equirectangular => {
+                let longitude = (s - 0.5) * 2.0 * PI;
+                let latitude = (0.5 - t) * PI;
+
+                let horizontal = longitude.sin() * self.u - longitude.cos() * self.w;
+                Some(latitude.cos() * horizontal + latitude.sin() * self.v)
+            }
+            Projection::Fisheye { mapping, fov } => {
+                // image coordinates scaled to 1 at the edge of the circle
+                let shorter = self.image_width.min(self.image_height) as f64;
+                let x = (2.0 * s - 1.0) * (self.image_width as f64) / shorter;
+                let y = (1.0 - 2.0 * t) * (self.image_height as f64) / shorter;
+
+                let r = (x * x + y * y).sqrt();
+                if r > 1.0 {
+                    return None;
+                }
+
+                let theta_max = degrees_to_radians(fov) / 2.0;
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * theta_max,
+                    FisheyeMapping::Equisolid => 2.0 * (r * (theta_max / 2.0).sin()).asin(),
+                };
+
+                let sideways = if r > 0.0 { (x / r) * self.u + (y / r) * self.v } else { Vec3::zero() };
+                Some(theta.sin() * sideways - theta.cos() * self.w)
+            }
+            Projection::Perspective | Projection::Orthographic { .. } => None,
+        }
     }
 
     fn defocus_disk_sample(&self) -> Point3 {
-        let p = random_in_unit_disk();
-        self.center + (p[0] * self.defocus_disk_u) + (p[1] * self.defocus_disk_v)
+        let (x, y) = self.aperture.sample();
+        self.center + ((x / self.anamorphic_squeeze) * self.defocus_disk_u) + (y * self.defocus_disk_v)
     }
 
     /// Returns a vector in the [-.5,-.5] - [+.5,+.5] unit square
     fn sample_square(&self) -> Vec3 {
         Vec3::new(random_f64() - 0.5, random_f64() - 0.5, 0.0)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_direction(actual: Option<Vec3>, expected: Vec3) {
+        let actual = actual.expect("no direction");
+        assert!((actual - expected).length() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    /// Square fisheye camera looking down -z, with +x to the right
+    fn fisheye(mapping: FisheyeMapping, fov: f64) -> Camera {
+        let mut camera = Camera { projection: Projection::Fisheye { mapping, fov }, ..Camera::default() };
+        camera.initialize();
+        camera
+    }
+
+    #[test]
+    fn fisheye_mappings() {
+        let (sin45, cos45) = (PI / 4.0).sin_cos();
+        // half way out, equisolid bends 2 asin(sin 45° / 2) = 41.41° away
+        let (sin_solid, cos_solid) = (2.0 * (sin45 / 2.0).asin()).sin_cos();
+
+        for (mapping, sin_half, cos_half) in [
+            (FisheyeMapping::Equidistant, sin45, cos45),
+            (FisheyeMapping::Equisolid, sin_solid, cos_solid),
+        ] {
+            let camera = fisheye(mapping, 180.0);
+
+            assert_direction(camera.panoramic_direction(0.5, 0.5), Vec3::new(0.0, 0.0, -1.0));
+            assert_direction(camera.panoramic_direction(0.75, 0.5), Vec3::new(sin_half, 0.0, -cos_half));
+            assert_direction(camera.panoramic_direction(0.5, 0.25), Vec3::new(0.0, sin_half, -cos_half));
+            assert_direction(camera.panoramic_direction(1.0, 0.5), Vec3::new(1.0, 0.0, 0.0));
+            assert_direction(camera.panoramic_direction(0.5, 1.0), Vec3::new(0.0, -1.0, 0.0));
+            assert!(camera.panoramic_direction(1.0, 0.0).is_none());
+        }
+
+        // the edge of a narrower circle is half its field of view away
+        let camera = fisheye(FisheyeMapping::Equisolid, 120.0);
+        let (sin60, cos60) = (PI / 3.0).sin_cos();
+        assert_direction(camera.panoramic_direction(0.0, 0.5), Vec3::new(-sin60, 0.0, -cos60));
+    }
+    #[test]
+    fn lens_settings_field_of_view() {
+        // a normal lens on full frame, and the same view on a smaller sensor
+        let full_frame = LensSettings::new(50.0, 2.0);
+        assert!((full_frame.vfov(1.5) - 2.0 * (12.0f64 / 50.0).atan().to_degrees()).abs() < 1e-9);
+
+        let aps_c = LensSettings::new(50.0 / 1.5, 2.0).with_sensor(24.0, 16.0);
+        assert!((aps_c.vfov(1.5) - full_frame.vfov(1.5)).abs() < 1e-9);
+
+        // a wider image fills the sensor across and crops it vertically
+        assert!((full_frame.vfov(16.0 / 9.0) - 2.0 * (10.125f64 / 50.0).atan().to_degrees()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn lens_settings_scale() {
+        // the same lens and view in meters and in centimeters
+        let meters = LensSettings::new(85.0, 1.8);
+        let centimeters = LensSettings::new(85.0, 1.8).with_scale(0.1);
+        assert!((meters.defocus_angle(2.0) - centimeters.defocus_angle(200.0)).abs() < 1e-9);
+
+        // 85 / 1.8 mm of aperture seen from 2 m
+        let expected = 2.0 * (0.0425f64 / 1.8 / 2.0).atan().to_degrees();
+        assert!((meters.defocus_angle(2.0) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn lens_settings_focus_by_magnification() {
+        assert_eq!(LensSettings::new(100.0, 2.8).focus_dist(), None);
+
+        // life size at twice the focal length, a tenth at eleven times it
+        let macro_lens = LensSettings::new(100.0, 2.8).with_magnification(1.0);
+        assert!((macro_lens.focus_dist().unwrap() - 0.2).abs() < 1e-12);
+        let portrait = LensSettings::new(100.0, 2.8).with_magnification(0.1);
+        assert!((portrait.focus_dist().unwrap() - 1.1).abs() < 1e-12);
+
+        let mut camera = Camera { lens_settings: Some(portrait), ..Camera::default() };
+        camera.initialize();
+        assert!((camera.focus_dist - 1.1).abs() < 1e-12);
+        assert!((camera.defocus_angle - portrait.defocus_angle(1.1)).abs() < 1e-12);
+
+        // autofocus decides instead
+        let mut camera = Camera { lens_settings: Some(portrait), autofocus: Some((50, 50)), ..Camera::default() };
+        camera.initialize();
+        assert_eq!(camera.focus_dist, 10.0);
+    }
+}
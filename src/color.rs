@@ -11,6 +11,11 @@ impl Color {
 }
 
 
+/// Relative luminance of a linear color
+pub fn luminance(c: Color) -> f64 {
+    0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z()
+}
+
 pub fn linear_to_gamma(linear_component: f64) -> f64 {
     if linear_component > 0.0 {
         return linear_component.sqrt();
@@ -19,7 +24,7 @@ pub fn linear_to_gamma(linear_component: f64) -> f64 {
     0.0
 }
 
-pub fn write_color<W: Write>(out: &mut W, pixel_color: &Color) -> () {
+pub fn write_color<W: Write>(out: &mut W, pixel_color: &Color) {
     let r = pixel_color.x();
     let g = pixel_color.y();
     let b = pixel_color.z();
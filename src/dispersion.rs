@@ -0,0 +1,67 @@
+/// How the index of refraction of a transparent material changes with
+/// wavelength, which splits white light into a rainbow
+pub enum Dispersion {
+    /// n = a + b / λ², with λ in micrometers
+    Cauchy { a: f64, b: f64 },
+    /// n² = 1 + Σ b λ² / (λ² - c), with λ in micrometers and c in µm²
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    pub fn cauchy(a: f64, b: f64) -> Self {
+        Dispersion::Cauchy { a, b }
+    }
+
+    pub fn sellmeier(b: [f64; 3], c: [f64; 3]) -> Self {
+        Dispersion::Sellmeier { b, c }
+    }
+
+    /// Schott N-BK7, the common crown glass of lenses and prisms
+    pub fn bk7() -> Self {
+        Self::sellmeier([1.039_612_12, 0.231_792_344, 1.010_469_45], [0.006_000_698_67, 0.020_017_914_4, 103.560_653])
+    }
+
+    /// Schott SF11, a dense flint glass that disperses strongly
+    pub fn sf11() -> Self {
+        Self::sellmeier([1.737_596_95, 0.313_747_346, 1.898_781_01], [0.013_188_707, 0.062_306_814_2, 155.236_29])
+    }
+
+    pub fn diamond() -> Self {
+        Self::sellmeier([4.3356, 0.3306, 0.0], [0.1060 * 0.1060, 0.1750 * 0.1750, 0.0])
+    }
+
+    /// Index of refraction at [lambda] nanometers
+    pub fn refraction_index(&self, lambda: f64) -> f64 {
+        let micrometers = lambda / 1000.0;
+        let l2 = micrometers * micrometers;
+
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / l2,
+            Dispersion::Sellmeier { b, c } => {
+                let mut n2 = 1.0;
+                for i in 0..3 {
+                    n2 += b[i] * l2 / (l2 - c[i]);
+                }
+                n2.sqrt()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cauchy_fit_matches_sellmeier_bk7() {
+        let cauchy = Dispersion::cauchy(1.5046, 0.00420);
+        let sellmeier = Dispersion::bk7();
+
+        // the Fraunhofer F, d and C lines
+        for lambda in [486.1, 587.6, 656.3] {
+            let (n_cauchy, n_sellmeier) = (cauchy.refraction_index(lambda), sellmeier.refraction_index(lambda));
+            assert!((n_cauchy - n_sellmeier).abs() < 1e-3, "{} != {} at {} nm", n_cauchy, n_sellmeier, lambda);
+        }
+        assert!((sellmeier.refraction_index(587.6) - 1.5168).abs() < 1e-4);
+    }
+}
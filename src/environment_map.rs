@@ -0,0 +1,162 @@
+use std::f64::consts::PI;
+use image::ImageError;
+use crate::background::Background;
+use crate::color::{luminance, Color};
+use crate::rt_weekend::{degrees_to_radians, random_f64};
+use crate::sampling::Distribution2D;
+use crate::vec3::{unit_vector, Vec3};
+
+/// Equirectangular (latitude-longitude) image surrounding the scene.
+/// The top row of the image is straight up (+y), and the horizontal
+/// center looks down +x
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    /// luminance of every texel, weighted by the solid angle it covers
+    distribution: Distribution2D,
+
+    /// rotation of the map around the vertical axis in degrees
+    pub rotation: f64,
+    /// scale factor applied to the radiance of every texel
+    pub intensity: f64,
+}
+
+impl EnvironmentMap {
+    /// Loads a high dynamic range image (`.hdr`, `.exr`).
+    /// NB: texel values are used as is, so they are expected to be linear
+    pub fn load(path: &str) -> Result<Self, ImageError> {
+        let image = image::open(path)?.into_rgb32f();
+
+        let pixels = image
+            .pixels()
+            .map(|p| Color::new(p[0] as f64, p[1] as f64, p[2] as f64))
+            .collect();
+
+        Ok(Self::from_pixels(image.width() as usize, image.height() as usize, pixels))
+    }
+
+    /// `pixels` are row-major, starting at the top left
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        let mut weights = Vec::with_capacity(width * height);
+
+        for j in 0..height {
+            let sin_theta = (PI * ((j as f64) + 0.5) / (height as f64)).sin();
+
+            for i in 0..width {
+                weights.push(luminance(pixels[j * width + i]) * sin_theta);
+            }
+        }
+
+        Self {
+            width,
+            height,
+            pixels,
+            distribution: Distribution2D::new(&weights, width, height),
+            rotation: 0.0,
+            intensity: 1.0,
+        }
+    }
+
+    fn texel(&self, u: f64, v: f64) -> Color {
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+
+        self.pixels[j * self.width + i]
+    }
+
+    /// Rotates `direction` around the vertical axis by `degrees`
+    fn rotate(direction: Vec3, degrees: f64) -> Vec3 {
+        let (sin_theta, cos_theta) = degrees_to_radians(degrees).sin_cos();
+
+        Vec3::new(
+            cos_theta * direction.x() + sin_theta * direction.z(),
+            direction.y(),
+            -sin_theta * direction.x() + cos_theta * direction.z(),
+        )
+    }
+
+    /// Image coordinates in [0,1]² of a unit direction in map space,
+    /// with v growing downwards
+    fn direction_to_uv(direction: Vec3) -> (f64, f64) {
+        let theta = direction.y().clamp(-1.0, 1.0).acos();
+        let phi = (-direction.z()).atan2(direction.x()) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    fn uv_to_direction(u: f64, v: f64) -> Vec3 {
+        let (sin_theta, cos_theta) = (v * PI).sin_cos();
+        let (sin_phi, cos_phi) = (u * 2.0 * PI).sin_cos();
+
+        Vec3::new(-sin_theta * cos_phi, cos_theta, sin_theta * sin_phi)
+    }
+}
+
+impl Background for EnvironmentMap {
+    fn value(&self, direction: Vec3) -> Color {
+        let local = Self::rotate(unit_vector(direction), -self.rotation);
+        let (u, v) = Self::direction_to_uv(local);
+
+        self.intensity * self.texel(u, v)
+    }
+
+    fn sample(&self) -> Option<(Vec3, f64)> {
+        let ((u, v), map_pdf) = self.distribution.sample(random_f64(), random_f64());
+        let sin_theta = (v * PI).sin();
+
+        if map_pdf <= 0.0 || sin_theta <= 0.0 {
+            return None;
+        }
+
+        // change of variables from image space to solid angle
+        let pdf = map_pdf / (2.0 * PI * PI * sin_theta);
+
+        Some((Self::rotate(Self::uv_to_direction(u, v), self.rotation), pdf))
+    }
+
+    fn pdf(&self, direction: Vec3) -> f64 {
+        let local = Self::rotate(unit_vector(direction), -self.rotation);
+        let (u, v) = Self::direction_to_uv(local);
+        let sin_theta = (v * PI).sin();
+
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::dot;
+
+    const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/images/environment.hdr");
+
+    #[test]
+    fn loads_a_radiance_hdr() {
+        let environment = EnvironmentMap::load(FIXTURE).unwrap();
+
+        assert_eq!((environment.width, environment.height), (64, 32));
+
+        // blue sky overhead, over brown ground
+        let up = environment.value(Vec3::new(0.0, 1.0, 0.0));
+        let down = environment.value(Vec3::new(0.0, -1.0, 0.0));
+        assert!(up.z() > up.x() && up.z() > 0.4);
+        assert!(down.x() > down.z() && down.x() < 0.1);
+    }
+
+    #[test]
+    fn samples_the_sun_most_often() {
+        let environment = EnvironmentMap::load(FIXTURE).unwrap();
+        let sun = unit_vector(Vec3::new(0.3, 0.6, -0.74));
+
+        let hits = (0..1000)
+            .filter_map(|_| environment.sample())
+            .filter(|(direction, _)| dot(unit_vector(*direction), sun) > 0.95)
+            .count();
+        assert!(hits > 500, "{} of 1000 samples near the sun", hits);
+    }
+}
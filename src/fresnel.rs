@@ -0,0 +1,177 @@
+use std::f64::consts::PI;
+use std::ops::{Add, Div, Mul, Sub};
+use crate::color::Color;
+
+/// Unpolarized Fresnel reflectance of a dielectric interface, for light
+/// arriving at [cos_theta_i] from the side where the relative index of
+/// refraction (transmitted over incident) is [eta].
+/// A negative [cos_theta_i] means arriving from the other side
+pub fn fr_dielectric(cos_theta_i: f64, eta: f64) -> f64 {
+    let (cos_theta_i, eta) = if cos_theta_i < 0.0 { (-cos_theta_i, 1.0 / eta) } else { (cos_theta_i, eta) };
+    let cos_theta_i = cos_theta_i.min(1.0);
+
+    let sin2_theta_t = (1.0 - cos_theta_i * cos_theta_i) / (eta * eta);
+    if sin2_theta_t >= 1.0 {
+        // total internal reflection
+        return 1.0;
+    }
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+
+    let r_parallel = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let r_perpendicular = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
+}
+
+/// Fresnel reflectance of a conductor with complex index of refraction
+/// [eta] + i[k], for one wavelength
+pub fn fr_complex(cos_theta_i: f64, eta: f64, k: f64) -> f64 {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let eta = Complex::new(eta, k);
+
+    let sin2_theta_i = Complex::real(1.0 - cos_theta_i * cos_theta_i);
+    let sin2_theta_t = sin2_theta_i / (eta * eta);
+    let cos_theta_t = (Complex::real(1.0) - sin2_theta_t).sqrt();
+    let cos_i = Complex::real(cos_theta_i);
+
+    let r_parallel = (eta * cos_i - cos_theta_t) / (eta * cos_i + cos_theta_t);
+    let r_perpendicular = (cos_i - eta * cos_theta_t) / (cos_i + eta * cos_theta_t);
+
+    (r_parallel.norm() + r_perpendicular.norm()) / 2.0
+}
+
+/// [fr_complex] for each color channel
+pub fn fr_complex_color(cos_theta_i: f64, eta: Color, k: Color) -> Color {
+    Color::new(
+        fr_complex(cos_theta_i, eta.x(), k.x()),
+        fr_complex(cos_theta_i, eta.y(), k.y()),
+        fr_complex(cos_theta_i, eta.z(), k.z()),
+    )
+}
+
+/// Reflectance of a film of index [film_eta], [thickness] nanometers
+/// thick, between an incident medium of index [eta_i] and a substrate of
+/// complex index [eta_t], for light of one [wavelength] in nanometers.
+/// Sums up the reflections bouncing inside the film (the Airy formula),
+/// which interfere and color the reflection
+pub fn fr_thin_film(cos_theta_i: f64, eta_i: f64, film_eta: f64, eta_t: Complex, thickness: f64, wavelength: f64) -> f64 {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let sin2_theta_i = 1.0 - cos_theta_i * cos_theta_i;
+    let one = Complex::real(1.0);
+
+    // cosines in the film and the substrate, complex past a critical angle
+    let n0 = Complex::real(eta_i);
+    let n1 = Complex::real(film_eta);
+    let cos0 = Complex::real(cos_theta_i);
+    let cos1 = (one - Complex::real(sin2_theta_i * (eta_i / film_eta).powi(2))).sqrt();
+    let cos2 = (one - Complex::real(sin2_theta_i * eta_i * eta_i) / (eta_t * eta_t)).sqrt();
+
+    // phase picked up by one round trip through the film
+    let phase = Complex::real(4.0 * PI * thickness / wavelength) * n1 * cos1;
+    let round_trip = (Complex::new(0.0, 1.0) * phase).exp();
+
+    let airy = |r01: Complex, r12: Complex| {
+        ((r01 + r12 * round_trip) / (one + r01 * r12 * round_trip)).norm()
+    };
+    let r_perpendicular = airy(r_s(n0, cos0, n1, cos1), r_s(n1, cos1, eta_t, cos2));
+    let r_parallel = airy(r_p(n0, cos0, n1, cos1), r_p(n1, cos1, eta_t, cos2));
+
+    ((r_perpendicular + r_parallel) / 2.0).min(1.0)
+}
+
+/// Amplitude reflection coefficient for light polarized perpendicular to
+/// the plane of incidence, from medium a into medium b
+fn r_s(eta_a: Complex, cos_a: Complex, eta_b: Complex, cos_b: Complex) -> Complex {
+    (eta_a * cos_a - eta_b * cos_b) / (eta_a * cos_a + eta_b * cos_b)
+}
+
+/// Amplitude reflection coefficient for light polarized parallel to the
+/// plane of incidence, from medium a into medium b
+fn r_p(eta_a: Complex, cos_a: Complex, eta_b: Complex, cos_b: Complex) -> Complex {
+    (eta_b * cos_a - eta_a * cos_b) / (eta_b * cos_a + eta_a * cos_b)
+}
+
+/// Schlick's approximation, from the reflectance [f0] at normal incidence
+pub fn fr_schlick(cos_theta_i: f64, f0: Color) -> Color {
+    let m = (1.0 - cos_theta_i.clamp(0.0, 1.0)).powi(5);
+    f0 + m * (Color::new(1.0, 1.0, 1.0) - f0)
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    pub fn real(re: f64) -> Self {
+        Self { re, im: 0.0 }
+    }
+
+    /// Squared magnitude
+    pub fn norm(&self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn exp(self) -> Self {
+        let magnitude = self.re.exp();
+        Self::new(magnitude * self.im.cos(), magnitude * self.im.sin())
+    }
+
+    /// Principal square root
+    pub fn sqrt(self) -> Self {
+        let n = self.norm().sqrt();
+        if n == 0.0 {
+            return Self::real(0.0);
+        }
+
+        let t1 = (0.5 * (n + self.re.abs())).sqrt();
+        let t2 = 0.5 * self.im / t1;
+
+        if self.re >= 0.0 {
+            Self::new(t1, t2)
+        } else {
+            Self::new(t2.abs(), t1.copysign(self.im))
+        }
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::new(self.re * rhs.re - self.im * rhs.im, self.re * rhs.im + self.im * rhs.re)
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        let scale = 1.0 / rhs.norm();
+        Self::new(
+            scale * (self.re * rhs.re + self.im * rhs.im),
+            scale * (self.im * rhs.re - self.re * rhs.im),
+        )
+    }
+}
@@ -1,4 +1,3 @@
-use std::ops::Neg;
 use std::sync::Arc;
 use crate::color::Color;
 use crate::interval::Interval;
@@ -12,22 +11,33 @@ pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
     pub t: f64,
+    /// surface coordinates of [p], for texture lookups
+    pub u: f64,
+    pub v: f64,
+    /// how [p] moves along the surface with [u] and [v], for tangent frames
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
     pub front_face: bool,
     pub mat: Arc<dyn Material>
 }
 
-impl HitRecord {
-
-    pub fn default() -> Self {
+impl Default for HitRecord {
+    fn default() -> Self {
         Self {
             p: Point3::zero(),
             normal: Vec3::zero(),
             t: 0.0,
+            u: 0.0,
+            v: 0.0,
+            dpdu: Vec3::zero(),
+            dpdv: Vec3::zero(),
             front_face: false,
             mat: Arc::new(Lambertian::new(Color::new(0.0, 0.0, 0.0)))
         }
     }
+}
 
+impl HitRecord {
     /// Set the hit record normal vector
     /// NB: [outward_normal] is assumed to have unit length.
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: &Vec3) {
@@ -37,6 +47,16 @@ impl HitRecord {
 
 }
 
-pub trait Hittable {
+pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool;
+
+    /// Solid angle density of [random] picking [direction] from [origin]
+    fn pdf_value(&self, _origin: Point3, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Random direction from [origin] toward the object, for sampling it as a light
+    fn random(&self, _origin: Point3) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
 }
\ No newline at end of file
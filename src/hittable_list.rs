@@ -3,15 +3,12 @@ use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
 use crate::ray::Ray;
 
+#[derive(Default)]
 pub struct HittableList {
     pub objects: Vec<Arc<dyn Hittable>>,
 }
 
 impl HittableList {
-    pub fn default() -> Self {
-        Self { objects: vec![] }
-    }
-
     pub fn with_object(object: Arc<dyn Hittable>) -> Self {
         let mut list = Self::default();
         list.add(object);
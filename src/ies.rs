@@ -0,0 +1,419 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use crate::onb::Onb;
+use crate::vec3::{dot, Vec3};
+
+#[derive(Debug)]
+pub enum IesError {
+    Io(std::io::Error),
+    /// the header never reaches the `TILT=` line
+    MissingTilt,
+    /// the file ends before all values are read
+    UnexpectedEnd,
+    /// a value that should be a number isn't
+    InvalidNumber(String),
+    /// the values are read but don't describe a usable profile
+    Invalid(String),
+}
+
+impl Display for IesError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IesError::Io(e) => write!(f, "{}", e),
+            IesError::MissingTilt => write!(f, "missing TILT= line"),
+            IesError::UnexpectedEnd => write!(f, "unexpected end of file"),
+            IesError::InvalidNumber(token) => write!(f, "invalid number '{}'", token),
+            IesError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for IesError {}
+
+impl From<std::io::Error> for IesError {
+    fn from(e: std::io::Error) -> Self {
+        IesError::Io(e)
+    }
+}
+
+/// Photometric web from an IES LM-63 file: candela values over a grid
+/// of vertical and horizontal angles (type C photometry). Vertical angle
+/// 0 points straight down the fixture's axis, horizontal angles go
+/// around it. Only the shape of the web is kept, relative to its peak
+/// intensity: a light using the profile gives the peak its own intensity
+pub struct IesProfile {
+    vertical_angles: Vec<f64>,
+    horizontal_angles: Vec<f64>,
+    /// one row of vertical values per horizontal angle
+    candela: Vec<Vec<f64>>,
+    max_candela: f64,
+}
+
+impl IesProfile {
+    pub fn load(path: &str) -> Result<Self, IesError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, IesError> {
+        // keyword header lines run up to the TILT= line
+        let mut lines = text.lines();
+        let tilt = loop {
+            match lines.next() {
+                Some(line) if line.trim_start().starts_with("TILT=") => break line.trim_start()[5..].trim(),
+                Some(_) => continue,
+                None => return Err(IesError::MissingTilt),
+            }
+        };
+
+        let mut tokens = Tokens::new(lines);
+
+        // lamp tilt data only matters for fixtures mounted at an angle
+        if tilt == "INCLUDE" {
+            tokens.next()?; // lamp to luminaire geometry
+            let count = count(tokens.next()?)?;
+            if count.checked_mul(2).is_none_or(|n| n > tokens.remaining()) {
+                return Err(IesError::Invalid(format!("{} tilt angles don't fit in the values left", count)));
+            }
+            for _ in 0..2 * count {
+                tokens.next()?;
+            }
+        }
+
+        let _lamps = tokens.next()?;
+        let _lumens_per_lamp = tokens.next()?;
+        let multiplier = tokens.next()?;
+        let vertical_count = count(tokens.next()?)?;
+        let horizontal_count = count(tokens.next()?)?;
+        let photometric_type = tokens.next()?;
+        let _units = tokens.next()?;
+        let _width = tokens.next()?;
+        let _length = tokens.next()?;
+        let _height = tokens.next()?;
+        let ballast_factor = tokens.next()?;
+        let _ballast_lamp_factor = tokens.next()?;
+        let _input_watts = tokens.next()?;
+
+        if photometric_type != 1.0 {
+            return Err(IesError::Invalid(format!("unsupported photometric type {}", photometric_type)));
+        }
+
+        // check the counts before trusting them with any allocation
+        let needed = vertical_count
+            .checked_mul(horizontal_count)
+            .and_then(|n| n.checked_add(vertical_count))
+            .and_then(|n| n.checked_add(horizontal_count));
+        if needed.is_none_or(|n| n > tokens.remaining()) {
+            return Err(IesError::Invalid(format!(
+                "{} by {} angles don't fit in the values left", vertical_count, horizontal_count
+            )));
+        }
+
+        let vertical_angles = (0..vertical_count).map(|_| tokens.next()).collect::<Result<Vec<f64>, IesError>>()?;
+        let horizontal_angles = (0..horizontal_count).map(|_| tokens.next()).collect::<Result<Vec<f64>, IesError>>()?;
+
+        let mut candela = Vec::with_capacity(horizontal_count);
+        for _ in 0..horizontal_count {
+            let row = (0..vertical_count)
+                .map(|_| tokens.next().map(|c| c * multiplier * ballast_factor))
+                .collect::<Result<Vec<f64>, IesError>>()?;
+            candela.push(row);
+        }
+
+        if !increasing(&vertical_angles) || !increasing(&horizontal_angles) {
+            return Err(IesError::Invalid("angles are not in increasing order".to_string()));
+        }
+        if vertical_angles[0] < 0.0 || vertical_angles[vertical_count - 1] > 180.0 {
+            return Err(IesError::Invalid("vertical angles outside 0-180 degrees".to_string()));
+        }
+        let (first, last) = (horizontal_angles[0], horizontal_angles[horizontal_count - 1]);
+        let bilateral = first == 90.0 && last == 270.0;
+        if !bilateral && (first != 0.0 || last > 360.0) {
+            return Err(IesError::Invalid("horizontal angles outside 0-360 degrees".to_string()));
+        }
+
+        let max_candela = candela.iter().flatten().fold(0.0, |max: f64, &c| max.max(c));
+        if max_candela <= 0.0 {
+            return Err(IesError::Invalid("profile emits no light".to_string()));
+        }
+
+        Ok(Self { vertical_angles, horizontal_angles, candela, max_candela })
+    }
+
+    /// Peak intensity of the fixture in candela
+    pub fn max_candela(&self) -> f64 {
+        self.max_candela
+    }
+
+    /// Intensity toward [vertical], [horizontal] (in degrees) relative to
+    /// the peak intensity, interpolated between the measured angles
+    pub fn relative_intensity(&self, vertical: f64, horizontal: f64) -> f64 {
+        let horizontal = self.fold_horizontal(horizontal.rem_euclid(360.0));
+
+        let (h0, h1, th) = self.bracket_horizontal(horizontal);
+        let (v0, v1, tv) = match self.vertical_angles.len() {
+            1 => (0, 0, 0.0),
+            _ if vertical < self.vertical_angles[0] => return 0.0,
+            _ if vertical > self.vertical_angles[self.vertical_angles.len() - 1] => return 0.0,
+            _ => bracket(&self.vertical_angles, vertical),
+        };
+
+        let row = |h: usize| (1.0 - tv) * self.candela[h][v0] + tv * self.candela[h][v1];
+
+        ((1.0 - th) * row(h0) + th * row(h1)) / self.max_candela
+    }
+
+    /// Relative intensity toward unit [direction], for a fixture whose
+    /// axis (vertical angle 0) is the w axis of [frame], and whose
+    /// horizontal angle 0 lies along its u axis
+    pub fn relative_intensity_toward(&self, frame: &Onb, direction: Vec3) -> f64 {
+        let vertical = dot(direction, frame.w()).clamp(-1.0, 1.0).acos().to_degrees();
+        let horizontal = dot(direction, frame.v()).atan2(dot(direction, frame.u())).to_degrees();
+
+        self.relative_intensity(vertical, horizontal)
+    }
+
+    /// Maps a horizontal angle onto the range the file covers,
+    /// following the symmetry implied by the first and last horizontal angles
+    fn fold_horizontal(&self, horizontal: f64) -> f64 {
+        let first = self.horizontal_angles[0];
+        let last = self.horizontal_angles[self.horizontal_angles.len() - 1];
+
+        if first == 90.0 {
+            // symmetric about the 90-270 degree plane
+            if horizontal < 90.0 {
+                180.0 - horizontal
+            } else if horizontal > 270.0 {
+                540.0 - horizontal
+            } else { horizontal }
+        } else if last == 0.0 {
+            // symmetric around the axis
+            0.0
+        } else if last <= 90.0 {
+            // symmetric in each quadrant
+            let h = if horizontal > 180.0 { 360.0 - horizontal } else { horizontal };
+            if h > 90.0 { 180.0 - h } else { h }
+        } else if last <= 180.0 {
+            // symmetric about the 0-180 degree plane
+            if horizontal > 180.0 { 360.0 - horizontal } else { horizontal }
+        } else {
+            horizontal
+        }
+    }
+
+    /// [bracket] for a folded horizontal angle. Files covering the full
+    /// circle may stop short of 360, which is the same as their 0
+    fn bracket_horizontal(&self, horizontal: f64) -> (usize, usize, f64) {
+        let angles = &self.horizontal_angles;
+        let last = angles.len() - 1;
+
+        if angles[0] == 0.0 && angles[last] > 180.0 && horizontal > angles[last] {
+            return (last, 0, (horizontal - angles[last]) / (360.0 - angles[last]));
+        }
+
+        bracket(angles, horizontal)
+    }
+}
+
+/// Numbers of the file after the `TILT=` line, separated by whitespace or commas
+struct Tokens<'a> {
+    tokens: Vec<&'a str>,
+    position: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(lines: impl Iterator<Item = &'a str>) -> Self {
+        let tokens = lines
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|token| !token.is_empty())
+            .collect();
+
+        Self { tokens, position: 0 }
+    }
+
+    fn next(&mut self) -> Result<f64, IesError> {
+        let token = *self.tokens.get(self.position).ok_or(IesError::UnexpectedEnd)?;
+        self.position += 1;
+
+        match token.parse::<f64>() {
+            Ok(value) if value.is_finite() => Ok(value),
+            _ => Err(IesError::InvalidNumber(token.to_string())),
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.tokens.len() - self.position
+    }
+}
+
+fn count(value: f64) -> Result<usize, IesError> {
+    if value < 1.0 || value.fract() != 0.0 {
+        return Err(IesError::Invalid(format!("invalid count {}", value)));
+    }
+
+    Ok(value as usize)
+}
+
+fn increasing(angles: &[f64]) -> bool {
+    angles.windows(2).all(|pair| pair[0] < pair[1])
+}
+
+/// Indices of the angles around [x] and how far [x] lies between them
+fn bracket(angles: &[f64], x: f64) -> (usize, usize, f64) {
+    let last = angles.len() - 1;
+    if x <= angles[0] {
+        return (0, 0, 0.0);
+    }
+    if x >= angles[last] {
+        return (last, last, 0.0);
+    }
+
+    let i = angles.partition_point(|&a| a <= x) - 1;
+    let t = (x - angles[i]) / (angles[i + 1] - angles[i]);
+
+    (i, i + 1, t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A profile with the given angle and candela lines after a header
+    /// for [vertical] by [horizontal] angles
+    fn parse(vertical: usize, horizontal: usize, data: &str) -> Result<IesProfile, IesError> {
+        IesProfile::parse(&format!(
+            "IESNA:LM-63-2002\n[TEST] test\nTILT=NONE\n1 1000 1 {} {} 1 2 0 0 0\n1 1 100\n{}",
+            vertical, horizontal, data
+        ))
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn parses_a_minimal_profile() {
+        let profile = parse(3, 1, "0 45 90\n0\n100 50 0\n").unwrap();
+
+        assert_close(profile.relative_intensity(0.0, 123.0), 1.0);
+        assert_close(profile.relative_intensity(22.5, 0.0), 0.75);
+        assert_close(profile.relative_intensity(90.0, 270.0), 0.0);
+        assert_close(profile.relative_intensity(120.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn skips_included_tilt_data() {
+        let text = "TILT=INCLUDE\n1\n2\n0 90\n1 1\n1 1000 2 1 1 1 2 0 0 0\n1 1 100\n0\n0\n50\n";
+        let profile = IesProfile::parse(text).unwrap();
+
+        assert_close(profile.relative_intensity(0.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn folds_quadrant_and_half_symmetry() {
+        let quadrant = parse(1, 2, "0\n0 90\n100\n50\n").unwrap();
+        assert_close(quadrant.relative_intensity(0.0, 135.0), 0.75);
+        assert_close(quadrant.relative_intensity(0.0, 270.0), 0.5);
+
+        let half = parse(1, 3, "0\n0 90 180\n100\n50\n0\n").unwrap();
+        assert_close(half.relative_intensity(0.0, 270.0), 0.5);
+        assert_close(half.relative_intensity(0.0, 315.0), 0.75);
+    }
+
+    #[test]
+    fn folds_bilateral_symmetry_about_90_270() {
+        let profile = parse(1, 3, "0\n90 180 270\n100\n50\n0\n").unwrap();
+
+        assert_close(profile.relative_intensity(0.0, 135.0), 0.75);
+        // mirrored across the 90-270 plane
+        assert_close(profile.relative_intensity(0.0, 45.0), 0.75);
+        assert_close(profile.relative_intensity(0.0, 0.0), 0.5);
+        assert_close(profile.relative_intensity(0.0, 315.0), 0.25);
+    }
+
+    #[test]
+    fn wraps_a_full_circle_short_of_360() {
+        let profile = parse(1, 4, "0\n0 90 180 270\n100\n50\n0\n20\n").unwrap();
+
+        assert_close(profile.relative_intensity(0.0, 315.0), 0.6);
+        assert_close(profile.relative_intensity(0.0, -45.0), 0.6);
+        assert_close(profile.relative_intensity(0.0, 270.0), 0.2);
+    }
+
+    #[test]
+    fn rejects_a_missing_tilt_line() {
+        let result = IesProfile::parse("IESNA:LM-63-2002\n1 1000 1 1 1 1 2 0 0 0\n");
+        assert!(matches!(result, Err(IesError::MissingTilt)));
+    }
+
+    #[test]
+    fn loads_the_fixture() {
+        let profile = IesProfile::load(concat!(env!("CARGO_MANIFEST_DIR"), "/images/fixture.ies")).unwrap();
+
+        assert_close(profile.max_candela(), 560.0);
+        assert_close(profile.relative_intensity(0.0, 0.0), 480.0 / 560.0);
+        assert_close(profile.relative_intensity(20.0, 0.0), 1.0);
+        // quadrant symmetry: 135 folds onto 45, 270 onto 90
+        assert_close(profile.relative_intensity(30.0, 135.0), 460.0 / 560.0);
+        assert_close(profile.relative_intensity(30.0, 270.0), 350.0 / 560.0);
+    }
+
+    #[test]
+    fn scales_by_the_multiplier_and_ballast_factor() {
+        let text = "TILT=NONE\n1 1000 2 1 1 1 2 0 0 0\n0.5 1 100\n0\n0\n150\n";
+        assert_close(IesProfile::parse(text).unwrap().max_candela(), 150.0);
+    }
+
+    #[test]
+    fn rejects_a_truncated_file() {
+        assert!(matches!(parse(3, 1, "0 45 90\n0\n100 50\n"), Err(IesError::Invalid(_))));
+        let text = "TILT=NONE\n1 1000 1 3 1 1 2 0 0 0\n1 1\n";
+        assert!(matches!(IesProfile::parse(text), Err(IesError::UnexpectedEnd)));
+    }
+
+    #[test]
+    fn rejects_counts_beyond_the_file() {
+        assert!(matches!(parse(1_000_000_000_000_000_000, 1, "0\n0\n100\n"), Err(IesError::Invalid(_))));
+        assert!(matches!(parse(1_000_000_000_000_000_000, 1_000_000_000, "0\n0\n100\n"), Err(IesError::Invalid(_))));
+        let text = "TILT=INCLUDE\n1\n1e18\n0\n1\n1 1000 1 1 1 1 2 0 0 0\n1 1 100\n0\n0\n50\n";
+        assert!(matches!(IesProfile::parse(text), Err(IesError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_a_bad_number() {
+        assert!(matches!(parse(3, 1, "0 45 ninety\n0\n100 50 0\n"), Err(IesError::InvalidNumber(token)) if token == "ninety"));
+        assert!(matches!(parse(3, 1, "0 45 90\n0\n100 nan 0\n"), Err(IesError::InvalidNumber(token)) if token == "nan"));
+        assert!(matches!(parse(3, 1, "0 45 90\n0\ninf 50 0\n"), Err(IesError::InvalidNumber(token)) if token == "inf"));
+    }
+
+    #[test]
+    fn rejects_a_bad_count() {
+        assert!(matches!(parse(0, 1, "0\n"), Err(IesError::Invalid(_))));
+        let text = "TILT=NONE\n1 1000 1 2.5 1 1 2 0 0 0\n1 1 100\n";
+        assert!(matches!(IesProfile::parse(text), Err(IesError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_other_photometric_types() {
+        let text = "TILT=NONE\n1 1000 1 1 1 2 2 0 0 0\n1 1 100\n0\n0\n100\n";
+        assert!(matches!(IesProfile::parse(text), Err(IesError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_unordered_angles() {
+        assert!(matches!(parse(3, 1, "0 90 45\n0\n100 50 0\n"), Err(IesError::Invalid(_))));
+        assert!(matches!(parse(1, 2, "0\n90 0\n100\n50\n"), Err(IesError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_angles_out_of_range() {
+        assert!(matches!(parse(2, 1, "0 190\n0\n100 50\n"), Err(IesError::Invalid(_))));
+        assert!(matches!(parse(1, 2, "0\n45 90\n100\n50\n"), Err(IesError::Invalid(_))));
+        assert!(matches!(parse(1, 2, "0\n0 400\n100\n50\n"), Err(IesError::Invalid(_))));
+        assert!(matches!(parse(1, 2, "0\n90 180\n100\n50\n"), Err(IesError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_a_dark_profile() {
+        assert!(matches!(parse(2, 1, "0 90\n0\n0 0\n"), Err(IesError::Invalid(_))));
+    }
+}
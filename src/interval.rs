@@ -3,12 +3,14 @@ pub struct Interval {
     pub max: f64,
 }
 
-impl Interval {
+impl Default for Interval {
     /// Default interval is empty
-    pub fn default() -> Self {
+    fn default() -> Self {
         Self { min: f64::INFINITY, max: -f64::INFINITY }
     }
+}
 
+impl Interval {
     pub fn new(min: f64, max: f64) -> Self {
         Self { min, max }
     }
@@ -0,0 +1,486 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use crate::ray::Ray;
+use crate::rt_weekend::random_f64;
+use crate::vec3::{dot, refract, unit_vector, Point3, Vec3};
+
+/// Rings of film positions, each with its own bounds of the exit pupil
+const PUPIL_SEGMENTS: usize = 64;
+/// Positions tried across the rear element per ring, in each direction
+const PUPIL_GRID: usize = 128;
+
+#[derive(Debug)]
+pub enum LensError {
+    Io(std::io::Error),
+    /// a value that should be a number isn't
+    InvalidNumber(String),
+    /// the values are read but don't describe a usable lens
+    Invalid(String),
+}
+
+impl Display for LensError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LensError::Io(e) => write!(f, "{}", e),
+            LensError::InvalidNumber(token) => write!(f, "invalid number '{}'", token),
+            LensError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for LensError {}
+
+impl From<std::io::Error> for LensError {
+    fn from(e: std::io::Error) -> Self {
+        LensError::Io(e)
+    }
+}
+
+/// One spherical surface of the lens, or the aperture stop where the
+/// radius is zero. Lengths are in millimeters
+struct LensElement {
+    /// positive when the surface bulges toward the scene
+    curvature_radius: f64,
+    /// distance along the axis to the next surface, or to the film
+    thickness: f64,
+    /// index of refraction behind the surface, zero for air
+    eta: f64,
+    aperture_radius: f64,
+}
+
+impl LensElement {
+    fn is_stop(&self) -> bool {
+        self.curvature_radius == 0.0
+    }
+
+    /// Index of refraction between this surface and the next one
+    fn medium(&self) -> f64 {
+        if self.eta == 0.0 { 1.0 } else { self.eta }
+    }
+}
+
+/// Axis-aligned bounds on the plane of the rear element
+#[derive(Copy, Clone)]
+struct PupilBounds {
+    x: (f64, f64),
+    y: (f64, f64),
+}
+
+impl PupilBounds {
+    fn area(&self) -> f64 {
+        (self.x.1 - self.x.0) * (self.y.1 - self.y.0)
+    }
+}
+
+/// Real lens made of spherical elements, traced ray by ray for the
+/// vignetting, distortion and focus breathing a thin lens doesn't have.
+///
+/// Works in its own space in millimeters, with the film at z = 0 and the
+/// scene toward negative z. Focusing moves the whole lens along the axis
+pub struct LensSystem {
+    /// from the front of the lens to the back
+    elements: Vec<LensElement>,
+    film_diagonal: f64,
+    /// world units per millimeter
+    scale: f64,
+    /// exit pupil as seen from each ring of film positions, on the +x axis
+    pupil_bounds: Vec<Option<PupilBounds>>,
+}
+
+impl LensSystem {
+    pub fn load(path: &str) -> Result<Self, LensError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Lens prescription with one surface per line, front first: curvature
+    /// radius, thickness, index of refraction and aperture diameter, with
+    /// `#` comments. A zero radius marks the aperture stop
+    pub fn parse(text: &str) -> Result<Self, LensError> {
+        let mut elements = Vec::new();
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let values = line
+                .split_whitespace()
+                .map(|token| token.parse::<f64>().map_err(|_| LensError::InvalidNumber(token.to_string())))
+                .collect::<Result<Vec<f64>, LensError>>()?;
+            if values.len() != 4 {
+                return Err(LensError::Invalid(format!("expected 4 values per surface, found {}", values.len())));
+            }
+
+            elements.push(LensElement {
+                curvature_radius: values[0],
+                thickness: values[1],
+                eta: values[2],
+                aperture_radius: values[3] / 2.0,
+            });
+        }
+
+        if elements.is_empty() {
+            return Err(LensError::Invalid("no lens surfaces".to_string()));
+        }
+        if elements.iter().any(|e| e.thickness < 0.0 || e.aperture_radius <= 0.0) {
+            return Err(LensError::Invalid("negative thickness or empty aperture".to_string()));
+        }
+
+        let lens = Self { elements, film_diagonal: 43.27, scale: 0.001, pupil_bounds: Vec::new() };
+        if lens.thick_lens().is_none() {
+            return Err(LensError::Invalid("lens blocks rays along its axis".to_string()));
+        }
+
+        Ok(lens)
+    }
+
+    /// Diagonal of the film in millimeters, full frame 35 mm by default
+    pub fn with_film_diagonal(mut self, film_diagonal: f64) -> Self {
+        self.film_diagonal = film_diagonal;
+        self
+    }
+
+    /// World units per millimeter, so meters by default
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Moves the lens so that things [distance] world units from the film
+    /// are in focus, as close as the lens allows, and finds the exit pupil
+    /// from there
+    pub fn focus(&mut self, distance: f64) {
+        let (front_principal, rear_principal, focal_length) = match self.thick_lens() {
+            Some(cardinal_points) => cardinal_points,
+            None => return,
+        };
+
+        // object and image distances from the principal planes, once the lens
+        // moved by delta toward the film, must meet the lens equation
+        let a = front_principal + distance / self.scale;
+        let b = -rear_principal;
+        let discriminant = ((a + b) * (a + b - 4.0 * focal_length)).max(0.0);
+        let delta = 0.5 * ((b - a) + discriminant.sqrt());
+
+        let last = self.elements.len() - 1;
+        self.elements[last].thickness = (self.elements[last].thickness - delta).max(0.0);
+
+        self.pupil_bounds = (0..PUPIL_SEGMENTS).map(|segment| self.bound_exit_pupil(segment)).collect();
+    }
+
+    /// Camera ray through the film position [s], [t] (both from 0 to 1
+    /// starting at the upper left corner) for an image [image_ratio] wide
+    /// per unit of height. The ray is in world units, with x to the right,
+    /// y up and the scene toward -z. Also returns the weight of the ray,
+    /// which falls off with the exit pupil and the angle to the film
+    pub fn sample_ray(&self, s: f64, t: f64, image_ratio: f64) -> Option<(Ray, f64)> {
+        let height = self.film_diagonal / (1.0 + image_ratio * image_ratio).sqrt();
+        let width = height * image_ratio;
+
+        // the lens flips the image, so the film is read the other way around
+        let film = Point3::new(-(s - 0.5) * width, (t - 0.5) * height, 0.0);
+        let r = (film.x() * film.x() + film.y() * film.y()).sqrt();
+
+        let segment = ((r / (self.film_diagonal / 2.0) * PUPIL_SEGMENTS as f64) as usize).min(PUPIL_SEGMENTS - 1);
+        let bounds = self.pupil_bounds.get(segment).copied().flatten()?;
+        let axial = self.pupil_bounds.first().copied().flatten()?;
+
+        // the bounds are for film positions on the +x axis, turned to this one
+        let (sin_phi, cos_phi) = if r > 0.0 { (film.y() / r, film.x() / r) } else { (0.0, 1.0) };
+        let x = bounds.x.0 + random_f64() * (bounds.x.1 - bounds.x.0);
+        let y = bounds.y.0 + random_f64() * (bounds.y.1 - bounds.y.0);
+        let pupil = Point3::new(cos_phi * x - sin_phi * y, sin_phi * x + cos_phi * y, self.rear_z());
+
+        let direction = unit_vector(pupil - film);
+        let out = self.trace_from_film(&Ray::new(film, direction))?;
+
+        let cos_theta = direction.z().abs();
+        let weight = cos_theta.powi(4) * bounds.area() / axial.area();
+
+        Some((Ray::new(self.scale * *out.origin(), *out.direction()), weight))
+    }
+
+    /// Position of the rear surface
+    fn rear_z(&self) -> f64 {
+        -self.elements[self.elements.len() - 1].thickness
+    }
+
+    /// Position of the front surface
+    fn front_z(&self) -> f64 {
+        -self.elements.iter().map(|e| e.thickness).sum::<f64>()
+    }
+
+    /// Follows [r] from the film side out of the front of the lens, or none
+    /// if an aperture blocks it
+    fn trace_from_film(&self, r: &Ray) -> Option<Ray> {
+        let mut origin = *r.origin();
+        let mut direction = *r.direction();
+        let mut z = 0.0;
+
+        for i in (0..self.elements.len()).rev() {
+            let element = &self.elements[i];
+            z -= element.thickness;
+
+            let (t, normal) = intersect_element(element, z, origin, direction)?;
+            origin += t * direction;
+            if origin.x() * origin.x() + origin.y() * origin.y() > element.aperture_radius * element.aperture_radius {
+                return None;
+            }
+
+            if let Some(normal) = normal {
+                let eta_t = if i > 0 { self.elements[i - 1].medium() } else { 1.0 };
+                direction = refract_through(direction, normal, element.medium() / eta_t)?;
+            }
+        }
+
+        Some(Ray::new(origin, direction))
+    }
+
+    /// Follows [r] from the scene side out of the back of the lens, or none
+    /// if an aperture blocks it
+    fn trace_from_scene(&self, r: &Ray) -> Option<Ray> {
+        let mut origin = *r.origin();
+        let mut direction = *r.direction();
+        let mut z = self.front_z();
+
+        for i in 0..self.elements.len() {
+            let element = &self.elements[i];
+
+            let (t, normal) = intersect_element(element, z, origin, direction)?;
+            origin += t * direction;
+            if origin.x() * origin.x() + origin.y() * origin.y() > element.aperture_radius * element.aperture_radius {
+                return None;
+            }
+
+            if let Some(normal) = normal {
+                let eta_i = if i > 0 { self.elements[i - 1].medium() } else { 1.0 };
+                direction = refract_through(direction, normal, eta_i / element.medium())?;
+            }
+
+            z += element.thickness;
+        }
+
+        Some(Ray::new(origin, direction))
+    }
+
+    /// Front and rear principal planes and the focal length of the lens,
+    /// from rays parallel to the axis traced through it both ways
+    fn thick_lens(&self) -> Option<(f64, f64, f64)> {
+        let height = 0.001 * self.film_diagonal;
+
+        let cardinal_points = |r_in: &Ray, r_out: &Ray| {
+            let (o, d) = (*r_out.origin(), *r_out.direction());
+            let focal = o.z() + (-o.x() / d.x()) * d.z();
+            let principal = o.z() + ((r_in.origin().x() - o.x()) / d.x()) * d.z();
+            (principal, focal)
+        };
+
+        let from_scene = Ray::new(Point3::new(height, 0.0, self.front_z() - 1.0), Vec3::new(0.0, 0.0, 1.0));
+        let (rear_principal, rear_focal) = cardinal_points(&from_scene, &self.trace_from_scene(&from_scene)?);
+
+        let from_film = Ray::new(Point3::new(height, 0.0, self.rear_z() + 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let (front_principal, _) = cardinal_points(&from_film, &self.trace_from_film(&from_film)?);
+
+        Some((front_principal, rear_principal, rear_focal - rear_principal))
+    }
+
+    /// Bounds of the rear element positions through which film positions
+    /// on the +x axis in ring [segment] see out of the lens
+    fn bound_exit_pupil(&self, segment: usize) -> Option<PupilBounds> {
+        let ring = self.film_diagonal / 2.0 / PUPIL_SEGMENTS as f64;
+        let (r0, r1) = (segment as f64 * ring, (segment + 1) as f64 * ring);
+
+        let rear = &self.elements[self.elements.len() - 1];
+        let extent = 1.5 * rear.aperture_radius;
+        let cell = 2.0 * extent / PUPIL_GRID as f64;
+
+        let mut bounds: Option<PupilBounds> = None;
+        for i in 0..PUPIL_GRID {
+            for j in 0..PUPIL_GRID {
+                let x = -extent + (i as f64 + 0.5) * cell;
+                let y = -extent + (j as f64 + 0.5) * cell;
+
+                let film_x = r0 + ((i * PUPIL_GRID + j) as f64 + 0.5) / (PUPIL_GRID * PUPIL_GRID) as f64 * (r1 - r0);
+                let film = Point3::new(film_x, 0.0, 0.0);
+                let pupil = Point3::new(x, y, self.rear_z());
+                if self.trace_from_film(&Ray::new(film, pupil - film)).is_none() {
+                    continue;
+                }
+
+                bounds = Some(match bounds {
+                    Some(b) => PupilBounds { x: (b.x.0.min(x), b.x.1.max(x)), y: (b.y.0.min(y), b.y.1.max(y)) },
+                    None => PupilBounds { x: (x, x), y: (y, y) },
+                });
+            }
+        }
+
+        // the grid may have stepped over the edges
+        bounds.map(|b| PupilBounds {
+            x: (b.x.0 - cell, b.x.1 + cell),
+            y: (b.y.0 - cell, b.y.1 + cell),
+        })
+    }
+}
+
+/// Where a ray from [origin] along [direction] meets the surface of
+/// [element] whose vertex is at [z], and the normal there facing the ray.
+/// The aperture stop is flat and has no normal
+fn intersect_element(element: &LensElement, z: f64, origin: Point3, direction: Vec3) -> Option<(f64, Option<Vec3>)> {
+    if element.is_stop() {
+        let t = (z - origin.z()) / direction.z();
+        return if t >= 0.0 { Some((t, None)) } else { None };
+    }
+
+    let radius = element.curvature_radius;
+    let oc = origin - Point3::new(0.0, 0.0, z + radius);
+    let a = direction.length_squared();
+    let half_b = dot(oc, direction);
+    let c = oc.length_squared() - radius * radius;
+
+    let discriminant = half_b * half_b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let sqrtd = discriminant.sqrt();
+
+    // the vertex side of the sphere is the nearer one when the ray heads
+    // toward the center of curvature
+    let use_closer = (direction.z() > 0.0) != (radius < 0.0);
+    let t = if use_closer { (-half_b - sqrtd) / a } else { (-half_b + sqrtd) / a };
+    if t < 0.0 {
+        return None;
+    }
+
+    let normal = unit_vector(oc + t * direction);
+    let normal = if dot(normal, direction) > 0.0 { -normal } else { normal };
+
+    Some((t, Some(normal)))
+}
+
+/// Refracts [direction] through a surface with [normal] facing it, or none
+/// on total internal reflection
+fn refract_through(direction: Vec3, normal: Vec3, eta_ratio: f64) -> Option<Vec3> {
+    let direction = unit_vector(direction);
+    let cos_theta = dot(-direction, normal).min(1.0);
+    let sin_theta_squared = 1.0 - cos_theta * cos_theta;
+    if eta_ratio * eta_ratio * sin_theta_squared > 1.0 {
+        return None;
+    }
+
+    Some(refract(direction, normal, eta_ratio))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Biconvex lens 0.1 mm thick with 100 mm radii in glass of index 1.5,
+    /// 100 mm in front of the film
+    const THIN_LENS: &str = "100 0.1 1.5 20\n-100 100 0 20\n";
+
+    /// Where [r] crosses the lens axis
+    fn axis_crossing(r: &Ray) -> f64 {
+        r.origin().z() - r.origin().x() / r.direction().x() * r.direction().z()
+    }
+
+    /// Follows a ray from the center of the film, close to the axis, out
+    /// of the lens, and returns where it crosses the axis in front of it
+    fn focused_at(lens: &LensSystem) -> f64 {
+        let r = Ray::new(Point3::zero(), Vec3::new(0.002, 0.0, -1.0));
+        axis_crossing(&lens.trace_from_film(&r).unwrap())
+    }
+
+    #[test]
+    fn parses_with_comments_and_blank_lines() {
+        let lens = LensSystem::parse("# a lens\n\n100 0.1 1.5 20 # front\n0 1 0 10\n-100 100 0 20\n").unwrap();
+
+        assert_eq!(lens.elements.len(), 3);
+        assert!(lens.elements[1].is_stop());
+        assert_eq!(lens.elements[0].aperture_radius, 10.0);
+    }
+
+    #[test]
+    fn rejects_a_wrong_value_count() {
+        assert!(matches!(LensSystem::parse("100 0.1 1.5\n"), Err(LensError::Invalid(_))));
+        assert!(matches!(LensSystem::parse("100 0.1 1.5 20 5\n"), Err(LensError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_a_bad_number() {
+        let result = LensSystem::parse("100 0.1 glass 20\n");
+        assert!(matches!(result, Err(LensError::InvalidNumber(token)) if token == "glass"));
+    }
+
+    #[test]
+    fn rejects_an_empty_prescription() {
+        assert!(matches!(LensSystem::parse("# nothing here\n"), Err(LensError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_a_zero_aperture_or_negative_thickness() {
+        assert!(matches!(LensSystem::parse("100 0.1 1.5 0\n-100 100 0 20\n"), Err(LensError::Invalid(_))));
+        assert!(matches!(LensSystem::parse("100 -0.1 1.5 20\n-100 100 0 20\n"), Err(LensError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_a_lens_blocking_the_axis() {
+        // a stop too small for even paraxial rays
+        assert!(matches!(LensSystem::parse("100 0.1 1.5 20\n0 1 0 0.01\n-100 100 0 20\n"), Err(LensError::Invalid(_))));
+    }
+
+    #[test]
+    fn thin_element_follows_the_lensmaker_equation() {
+        let lens = LensSystem::parse(THIN_LENS).unwrap();
+        let (_, _, focal_length) = lens.thick_lens().unwrap();
+
+        // 1/f = (n - 1)(1/R1 - 1/R2)
+        assert!((focal_length - 100.0).abs() < 0.1, "focal length {}", focal_length);
+    }
+
+    #[test]
+    fn focuses_on_axis_rays_at_the_distance() {
+        let mut lens = LensSystem::parse(THIN_LENS).unwrap();
+        lens.focus(1.0);
+
+        // a meter is 1000 mm in front of the film
+        let z = focused_at(&lens);
+        assert!((z + 1000.0).abs() < 1.0, "focused at {}", z);
+    }
+
+    #[test]
+    fn focuses_in_world_units_of_the_scale() {
+        // a world measured in millimeters
+        let mut lens = LensSystem::parse(THIN_LENS).unwrap().with_scale(1.0);
+        lens.focus(1000.0);
+
+        let z = focused_at(&lens);
+        assert!((z + 1000.0).abs() < 1.0, "focused at {}", z);
+    }
+
+    #[test]
+    fn film_diagonal_sets_the_field_of_view() {
+        // focused far away, so rays from one film position leave parallel,
+        // at an angle set by how far the position is from the axis
+        let mut lens = LensSystem::parse(THIN_LENS).unwrap().with_film_diagonal(10.0);
+        lens.focus(1e6);
+
+        let (r, _) = (0..100).find_map(|_| lens.sample_ray(0.0, 0.0, 1.0)).unwrap();
+        let d = r.direction();
+        let tan_theta = (d.x() * d.x() + d.y() * d.y()).sqrt() / d.z().abs();
+
+        // the corner is half the diagonal from the center
+        assert!((tan_theta - 5.0 / 100.0).abs() < 0.002, "tan theta {}", tan_theta);
+    }
+
+    #[test]
+    fn focuses_the_double_gauss_lens() {
+        let mut lens = LensSystem::parse(include_str!("../lenses/dgauss.50mm.dat")).unwrap();
+        let (_, _, focal_length) = lens.thick_lens().unwrap();
+        assert!((focal_length - 50.0).abs() < 1.0, "focal length {}", focal_length);
+
+        lens.focus(2.0);
+        let z = focused_at(&lens);
+        assert!((z + 2000.0).abs() < 2.0, "focused at {}", z);
+    }
+}
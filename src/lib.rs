@@ -0,0 +1,29 @@
+pub mod rt_weekend;
+pub mod vec3;
+pub mod color;
+pub mod ray;
+pub mod hittable;
+pub mod sphere;
+pub mod hittable_list;
+pub mod interval;
+pub mod camera;
+pub mod material;
+pub mod sampling;
+pub mod background;
+pub mod environment_map;
+pub mod onb;
+pub mod sky;
+pub mod light;
+pub mod ies;
+pub mod microfacet;
+pub mod fresnel;
+pub mod principled;
+pub mod texture;
+pub mod thin_film;
+pub mod spectrum;
+pub mod dispersion;
+pub mod quad;
+pub mod triangle;
+pub mod normal_map;
+pub mod aperture;
+pub mod lens_system;
@@ -0,0 +1,277 @@
+use std::sync::Arc;
+use crate::color::Color;
+use crate::hittable::{HitRecord, Hittable};
+use crate::ies::IesProfile;
+use crate::interval::Interval;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::rt_weekend::random_f64;
+use crate::vec3::{dot, unit_vector, Point3, Vec3};
+
+/// Light arriving at a shaded point from a sampled light source
+pub struct LightSample {
+    /// unit vector from the shaded point toward the light
+    pub direction: Vec3,
+    /// distance to the light along [direction], infinite for directional lights
+    pub distance: f64,
+    /// radiance arriving at the shaded point, before occlusion
+    pub radiance: Color,
+    /// solid angle density of [direction]; 1 for delta lights
+    pub pdf: f64,
+}
+
+/// Light source that next-event estimation samples directly.
+/// Delta lights (points, spots, directions) can only be reached this way,
+/// since scattered rays never hit them
+pub trait Light: Send + Sync {
+    fn sample(&self, p: Point3, time: f64) -> Option<LightSample>;
+
+    /// Solid angle density of [sample] picking the direction of [r],
+    /// given that [r] hit this light at [rec]
+    fn pdf(&self, _r: &Ray, _rec: &HitRecord) -> f64 {
+        0.0
+    }
+
+    fn is_delta(&self) -> bool {
+        true
+    }
+}
+
+/// Photometric profile attached to a light, and the frame it is aimed with
+struct Photometry {
+    profile: Arc<IesProfile>,
+    frame: Onb,
+}
+
+impl Photometry {
+    /// Scale applied to the light's intensity for light leaving along [direction]
+    fn scale(&self, direction: Vec3) -> f64 {
+        self.profile.relative_intensity_toward(&self.frame, direction)
+    }
+}
+
+/// Light leaving a single point evenly in all directions,
+/// unless shaped by a photometric profile
+pub struct PointLight {
+    position: Point3,
+    /// radiant intensity
+    intensity: Color,
+    photometry: Option<Photometry>,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> Self {
+        Self { position, intensity, photometry: None }
+    }
+
+    /// Modulates the intensity by direction, with the fixture axis along
+    /// [aim]. [intensity] becomes the intensity of the profile's peak
+    pub fn with_profile(mut self, profile: Arc<IesProfile>, aim: Vec3) -> Self {
+        self.photometry = Some(Photometry { profile, frame: Onb::new(aim) });
+        self
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, p: Point3, _time: f64) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance_squared = to_light.length_squared();
+        let direction = unit_vector(to_light);
+
+        let scale = match &self.photometry {
+            Some(photometry) => photometry.scale(-direction),
+            None => 1.0,
+        };
+        if scale <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance: distance_squared.sqrt(),
+            radiance: (scale / distance_squared) * self.intensity,
+            pdf: 1.0,
+        })
+    }
+}
+
+/// Point light restricted to a cone, fading out between
+/// its falloff start angle and its total width
+pub struct SpotLight {
+    position: Point3,
+    direction: Vec3,
+    intensity: Color,
+    cos_total_width: f64,
+    cos_falloff_start: f64,
+    photometry: Option<Photometry>,
+}
+
+impl SpotLight {
+    /// Angles are measured from the spot [direction], in degrees
+    pub fn new(position: Point3, direction: Vec3, intensity: Color, total_width: f64, falloff_start: f64) -> Self {
+        Self {
+            position,
+            direction: unit_vector(direction),
+            intensity,
+            cos_total_width: total_width.to_radians().cos(),
+            cos_falloff_start: falloff_start.min(total_width).to_radians().cos(),
+            photometry: None,
+        }
+    }
+
+    /// Modulates the intensity inside the cone by direction, with the
+    /// fixture axis along the spot direction
+    pub fn with_profile(mut self, profile: Arc<IesProfile>) -> Self {
+        self.photometry = Some(Photometry { profile, frame: Onb::new(self.direction) });
+        self
+    }
+
+    fn falloff(&self, cos_theta: f64) -> f64 {
+        if cos_theta >= self.cos_falloff_start {
+            return 1.0;
+        }
+        if cos_theta <= self.cos_total_width {
+            return 0.0;
+        }
+
+        let t = (cos_theta - self.cos_total_width) / (self.cos_falloff_start - self.cos_total_width);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, p: Point3, _time: f64) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let distance_squared = to_light.length_squared();
+        let direction = unit_vector(to_light);
+
+        let mut falloff = self.falloff(dot(-direction, self.direction));
+        if let Some(photometry) = &self.photometry {
+            falloff *= photometry.scale(-direction);
+        }
+        if falloff <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance: distance_squared.sqrt(),
+            radiance: (falloff / distance_squared) * self.intensity,
+            pdf: 1.0,
+        })
+    }
+}
+
+/// Parallel light coming from infinitely far away, like the sun
+pub struct DirectionalLight {
+    /// direction the light travels in
+    direction: Vec3,
+    /// irradiance on a surface facing the light
+    irradiance: Color,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, irradiance: Color) -> Self {
+        Self { direction: unit_vector(direction), irradiance }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _p: Point3, _time: f64) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction,
+            distance: f64::INFINITY,
+            radiance: self.irradiance,
+            pdf: 1.0,
+        })
+    }
+}
+
+/// Emissive geometry: samples directions toward [shape], and takes the
+/// radiance from whatever its material emits
+pub struct AreaLight {
+    shape: Arc<dyn Hittable>,
+}
+
+impl AreaLight {
+    pub fn new(shape: Arc<dyn Hittable>) -> Self {
+        Self { shape }
+    }
+}
+
+impl Light for AreaLight {
+    fn sample(&self, p: Point3, time: f64) -> Option<LightSample> {
+        let direction = unit_vector(self.shape.random(p));
+        let ray = Ray::timed(p, direction, time);
+
+        let mut rec = HitRecord::default();
+        if !self.shape.hit(&ray, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return None;
+        }
+
+        let pdf = self.shape.pdf_value(p, direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some(LightSample {
+            direction,
+            distance: rec.t,
+            radiance: rec.mat.emitted(&ray, &rec),
+            pdf,
+        })
+    }
+
+    fn pdf(&self, r: &Ray, rec: &HitRecord) -> f64 {
+        // only if it's this light's surface that [r] ran into
+        let mut light_rec = HitRecord::default();
+        let around_hit = Interval::new(rec.t - 1e-4 * rec.t.max(1.0), rec.t + 1e-4 * rec.t.max(1.0));
+        if !self.shape.hit(r, around_hit, &mut light_rec) {
+            return 0.0;
+        }
+
+        self.shape.pdf_value(*r.origin(), *r.direction())
+    }
+
+    fn is_delta(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Default)]
+pub struct LightList {
+    pub lights: Vec<Arc<dyn Light>>,
+}
+
+impl LightList {
+    pub fn add(&mut self, light: Arc<dyn Light>) {
+        self.lights.push(light);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Picks one light uniformly, returning it with the probability of picking it
+    pub fn choose(&self) -> Option<(&Arc<dyn Light>, f64)> {
+        if self.lights.is_empty() {
+            return None;
+        }
+
+        let count = self.lights.len();
+        let index = ((random_f64() * count as f64) as usize).min(count - 1);
+
+        Some((&self.lights[index], 1.0 / count as f64))
+    }
+
+    /// Density of [choose] followed by [Light::sample] picking the
+    /// direction of [r], which hit emissive geometry at [rec]
+    pub fn pdf(&self, r: &Ray, rec: &HitRecord) -> f64 {
+        if self.lights.is_empty() {
+            return 0.0;
+        }
+
+        let sum: f64 = self.lights.iter().map(|light| light.pdf(r, rec)).sum();
+        sum / self.lights.len() as f64
+    }
+}
@@ -1,33 +1,127 @@
+use std::env;
 use std::f64::consts::PI;
-use std::io::stdout;
+use std::process;
 use std::sync::Arc;
-use crate::camera::Camera;
-use crate::color::Color;
-use crate::hittable::{Hittable};
-use crate::hittable_list::HittableList;
-use crate::material::{Dielectric, Lambertian, Material};
-use crate::material::Metal;
-use crate::rt_weekend::{random_f64, random_f64_within};
-use crate::sphere::Sphere;
-use crate::vec3::{Point3, Vec3};
-
-mod rt_weekend;
-mod vec3;
-mod color;
-mod ray;
-mod hittable;
-mod sphere;
-mod hittable_list;
-mod interval;
-mod camera;
-mod material;
+use rtiow::aperture::Aperture;
+use rtiow::camera::{Camera, FisheyeMapping, LensSettings, Projection, Stereo, StereoLayout};
+use rtiow::color::Color;
+use rtiow::dispersion::Dispersion;
+use rtiow::environment_map::EnvironmentMap;
+use rtiow::hittable_list::HittableList;
+use rtiow::ies::IesProfile;
+use rtiow::lens_system::LensSystem;
+use rtiow::light::{AreaLight, DirectionalLight, LightList, PointLight, SpotLight};
+use rtiow::background::Gradient;
+use rtiow::material::{Coated, Cutout, Dielectric, DiffuseLight, Lambertian, MixMaterial, OrenNayar, Subsurface, TwoSided};
+use rtiow::material::{Material, Metal};
+use rtiow::principled::Principled;
+use rtiow::rt_weekend::{random_f64, random_f64_within};
+use rtiow::sky::Sky;
+use rtiow::spectrum::SampledSpectrum;
+use rtiow::sphere::Sphere;
+use rtiow::normal_map::NormalMapped;
+use rtiow::quad::Quad;
+use rtiow::texture::{CheckerTexture, ImageTexture};
+use rtiow::triangle::Triangle;
+use rtiow::thin_film::ThinFilm;
+use rtiow::vec3::{Point3, Vec3};
+
+/// Scenes by the name that picks them on the command line, the first by default
+static SCENES: &[(&str, fn())] = &[
+    ("bouncing_spheres", bouncing_spheres),
+    ("environment_lit", || environment_lit("images/environment.hdr")),
+    ("daylight", daylight),
+    ("light_sources", light_sources),
+    ("photometric_lights", || photometric_lights("images/fixture.ies")),
+    ("metals", metals),
+    ("frosted_glass", frosted_glass),
+    ("colored_glass", colored_glass),
+    ("principled", principled),
+    ("coatings", coatings),
+    ("rough_diffuse", rough_diffuse),
+    ("iridescence", iridescence),
+    ("spectral", spectral),
+    ("dispersion", dispersion),
+    ("subsurface", subsurface),
+    ("normal_mapping", normal_mapping),
+    ("cutouts", cutouts),
+    ("mixes", mixes),
+    ("two_sided", two_sided),
+    ("isometric", isometric),
+    ("equirectangular", || panorama(Projection::Equirectangular, None)),
+    ("fisheye", || panorama(Projection::Fisheye { mapping: FisheyeMapping::Equisolid, fov: 180.0 }, None)),
+    ("stereo", || panorama(Projection::Perspective, Some(Stereo::new(StereoLayout::SideBySide, 0.2, 4.0)))),
+    ("stereo_panorama", || panorama(Projection::Equirectangular, Some(Stereo::new(StereoLayout::OverUnder, 0.2, 4.0)))),
+    ("hexagon_bokeh", || bokeh(Aperture::Polygon { blades: 6, rotation: 15.0 }, 1.0)),
+    ("heart_bokeh", || bokeh(Aperture::image(Arc::new(mask(256, |u, v| {
+        // heart curve, point side down
+        let (x, y) = (2.4 * u - 1.2, 2.4 * v - 1.3);
+        (x * x + y * y - 1.0).powi(3) - x * x * y.powi(3) < 0.0
+    }))), 1.0)),
+    ("anamorphic_bokeh", || bokeh(Aperture::Circle, 2.0)),
+    ("real_lens", || real_lens("lenses/dgauss.50mm.dat")),
+    ("architecture", architecture),
+    ("miniature", miniature),
+    ("portrait", portrait),
+];
 
 fn main() {
+    let name = env::args().nth(1).unwrap_or_else(|| SCENES[0].0.to_string());
+
+    match SCENES.iter().find(|(scene, _)| *scene == name) {
+        Some((_, render)) => render(),
+        None => {
+            eprintln!("Unknown scene '{}', pick one of:", name);
+            for (scene, _) in SCENES {
+                eprintln!("  {}", scene);
+            }
+            process::exit(1);
+        }
+    }
+}
+
+/// Camera at [lookfrom] looking at [lookat], upright, for a 16:9 image 400
+/// pixels wide with the samples and depth most scenes use
+fn default_camera(lookfrom: Point3, lookat: Point3) -> Camera {
+    let mut camera = Camera::default();
+    camera.aspect_ratio = 16.0 / 9.0;
+    camera.image_width = 400;
+    camera.samples_per_pixel = 100;
+    camera.max_depth = 50;
+
+    camera.lookfrom = lookfrom;
+    camera.lookat = lookat;
+    camera.vup = Vec3::new(0.0, 1.0, 0.0);
+
+    camera
+}
+
+/// Sphere so large that its top is flat ground at y = 0
+fn ground(mat: Arc<dyn Material>) -> Arc<Sphere> {
+    Arc::new(Sphere::stationary(Point3::new(0.0, -1000.0, 0.0), 1000.0, mat))
+}
+
+/// The three large spheres of the book's cover: diffuse, glass and metal
+fn book_spheres() -> HittableList {
+    let mut spheres = HittableList::default();
+
+    let diffuse = Arc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1)));
+    spheres.add(Arc::new(Sphere::stationary(Point3::new(-4.0, 1.0, 0.0), 1.0, diffuse)));
+
+    let glass = Arc::new(Dielectric::new(1.5));
+    spheres.add(Arc::new(Sphere::stationary(Point3::new(0.0, 1.0, 0.0), 1.0, glass)));
+
+    let metal = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
+    spheres.add(Arc::new(Sphere::stationary(Point3::new(4.0, 1.0, 0.0), 1.0, metal)));
+
+    spheres
+}
+
+fn bouncing_spheres() {
     //world
     let mut world = HittableList::default();
 
-    let ground_material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
-    world.add(Arc::new(Sphere::stationary(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground_material)));
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
 
     for a in -11..11 {
         for b in -11..11 {
@@ -39,52 +133,853 @@ fn main() {
             );
 
             if (center - Point3::new(4.0, 0.2 , 0.0)).length() > 0.9 {
-                let sphere_material: Arc<dyn Material>;
-
                 if choose_mat < 0.8 {
                     // diffuse
                     let albedo: Color = Color::random() * Color::random();
-                    sphere_material = Arc::new(Lambertian::new(albedo));
+                    let sphere_material = Arc::new(Lambertian::new(albedo));
                     let center2 = center + Vec3::new(0.0, random_f64_within(0.0, 0.5), 0.0);
 
                     world.add(Arc::new(Sphere::moving(center, center2, 0.2, sphere_material)));
                 } else if choose_mat < 0.95 {
                     // metal
                     let albedo = Color::random_within(0.5, 1.0);
-                    let fuzz = random_f64_within(0.0, 0.5);
-                    sphere_material = Arc::new(Metal::new(albedo, fuzz));
+                    // GGX width grows with roughness squared, so up to 0.7 blurs
+                    // about as much as the book's fuzz of up to 0.5
+                    let roughness = random_f64_within(0.0, 0.7);
+                    let sphere_material = Arc::new(Metal::new(albedo, roughness));
                     world.add(Arc::new(Sphere::stationary(center, 0.2, sphere_material)));
                 } else {
                     // glass
-                    sphere_material = Arc::new(Dielectric::new(1.5));
+                    let sphere_material = Arc::new(Dielectric::new(1.5));
                     world.add(Arc::new(Sphere::stationary(center, 0.2, sphere_material)));
                 }
             }
         }
     }
 
-    let material1 = Arc::new(Dielectric::new(1.5));
-    world.add(Arc::new(Sphere::stationary(Point3::new(0.0, 1.0, 0.0), 1.0, material1)));
-
-    let material2 = Arc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1)));
-    world.add(Arc::new(Sphere::stationary(Point3::new(-4.0, 1.0, 0.0), 1.0, material2)));
-
-    let material3 = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
-    world.add(Arc::new(Sphere::stationary(Point3::new(4.0, 1.0, 0.0), 1.0 , material3)));
+    world.add(Arc::new(book_spheres()));
 
-    let mut camera = Camera::default();
-    camera.aspect_ratio = 16.0 / 9.0;
+    let mut camera = default_camera(Point3::new(13.0, 2.0, 3.0), Point3::new(0.0, 0.0, 0.0));
+    camera.vfov = 20.0;
     camera.image_width = 1200;
-    camera.samples_per_pixel = 100;
-    camera.max_depth = 50;
-
-    camera.vfov = 20;
-    camera.lookfrom = Point3::new(13.0, 2.0, 3.0);
-    camera.lookat = Point3::new(0.0, 0.0, 0.0);
-    camera.vup = Vec3::new(0.0, 1.0, 0.0);
 
     camera.defocus_angle = 0.6;
     camera.focus_dist = 10.0;
 
-    camera.render(&world);
+    camera.render(&world, &LightList::default());
+}
+
+fn environment_lit(path: &str) {
+    let mut environment = match EnvironmentMap::load(path) {
+        Ok(environment) => environment,
+        Err(e) => {
+            eprintln!("Unable to load environment map {}: {}", path, e);
+            return;
+        }
+    };
+    environment.rotation = 90.0;
+    environment.intensity = 1.0;
+
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
+    world.add(Arc::new(book_spheres()));
+
+    let mut camera = default_camera(Point3::new(13.0, 2.0, 3.0), Point3::new(0.0, 0.0, 0.0));
+    camera.vfov = 20.0;
+    camera.background = Arc::new(environment);
+
+    camera.render(&world, &LightList::default());
+}
+
+fn daylight() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
+    world.add(Arc::new(book_spheres()));
+
+    let mut camera = default_camera(Point3::new(13.0, 2.0, 3.0), Point3::new(0.0, 0.0, 0.0));
+    camera.vfov = 20.0;
+    // a sun larger than the real one casts softer shadows
+    camera.background = Arc::new(Sky::new(25.0, 200.0, 3.0).with_sun_radius(2.0));
+
+    camera.render(&world, &LightList::default());
+}
+
+fn light_sources() {
+    let mut world = HittableList::default();
+    let mut lights = LightList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
+    world.add(Arc::new(book_spheres()));
+
+    // emissive geometry is both part of the world and a light
+    let lamp = Arc::new(Sphere::stationary(
+        Point3::new(0.0, 4.0, -3.0),
+        0.5,
+        Arc::new(DiffuseLight::new(Color::new(8.0, 8.0, 6.0))),
+    ));
+    world.add(lamp.clone());
+    lights.add(Arc::new(AreaLight::new(lamp)));
+
+    lights.add(Arc::new(PointLight::new(Point3::new(-4.0, 3.0, 3.0), Color::new(10.0, 4.0, 2.0))));
+    lights.add(Arc::new(SpotLight::new(
+        Point3::new(6.0, 5.0, 2.0),
+        Vec3::new(-2.0, -5.0, -2.0),
+        Color::new(20.0, 20.0, 40.0),
+        25.0,
+        15.0,
+    )));
+    lights.add(Arc::new(DirectionalLight::new(Vec3::new(1.0, -1.0, -0.5), Color::new(0.2, 0.2, 0.25))));
+
+    let mut camera = default_camera(Point3::new(13.0, 2.0, 3.0), Point3::new(0.0, 0.0, 0.0));
+    camera.vfov = 20.0;
+    camera.background = Arc::new(Gradient::new(Color::zero(), Color::new(0.02, 0.02, 0.05)));
+
+    camera.render(&world, &lights);
+}
+
+fn photometric_lights(path: &str) {
+    let profile = match IesProfile::load(path) {
+        Ok(profile) => Arc::new(profile),
+        Err(e) => {
+            eprintln!("Unable to load IES profile {}: {}", path, e);
+            return;
+        }
+    };
+
+    let mut world = HittableList::default();
+    let mut lights = LightList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
+
+    let wall = Arc::new(Lambertian::new(Color::new(0.7, 0.7, 0.7)));
+    world.add(Arc::new(Sphere::stationary(Point3::new(0.0, 0.0, -1005.0), 1000.0, wall)));
+
+    let diffuse = Arc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1)));
+    world.add(Arc::new(Sphere::stationary(Point3::new(0.0, 1.0, 0.0), 1.0, diffuse)));
+
+    // wall washers aimed straight down
+    for x in [-4.0, 4.0] {
+        let fixture = PointLight::new(Point3::new(x, 4.0, -4.0), Color::new(12.0, 11.0, 9.0))
+            .with_profile(profile.clone(), Vec3::new(0.0, -1.0, 0.0));
+        lights.add(Arc::new(fixture));
+    }
+
+    let spot = SpotLight::new(Point3::new(0.0, 5.0, 2.0), Vec3::new(0.0, -1.0, -0.4), Color::new(12.0, 12.0, 12.0), 40.0, 30.0)
+        .with_profile(profile);
+    lights.add(Arc::new(spot));
+
+    let mut camera = default_camera(Point3::new(0.0, 2.0, 10.0), Point3::new(0.0, 1.5, 0.0));
+    camera.vfov = 40.0;
+    camera.background = Arc::new(Gradient::new(Color::zero(), Color::zero()));
+
+    camera.render(&world, &lights);
+}
+
+fn metals() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
+
+    // polished to rough, left to right
+    let presets: [fn(f64) -> Metal; 4] = [Metal::gold, Metal::copper, Metal::aluminum, Metal::silver];
+    for (row, preset) in presets.iter().enumerate() {
+        for column in 0..4 {
+            let roughness = column as f64 / 3.0;
+            let center = Point3::new(2.5 * (column as f64 - 1.5), 1.0, -2.5 * (row as f64 - 1.5));
+            world.add(Arc::new(Sphere::stationary(center, 1.0, Arc::new(preset(roughness)))));
+        }
+    }
+
+    let brushed = Arc::new(Metal::aluminum(0.0).with_anisotropic_roughness(0.1, 0.6));
+    world.add(Arc::new(Sphere::stationary(Point3::new(0.0, 1.0, 6.0), 1.0, brushed)));
+
+    let mut camera = default_camera(Point3::new(0.0, 9.0, 18.0), Point3::new(0.0, 0.5, 0.0));
+    camera.vfov = 35.0;
+    camera.background = Arc::new(Sky::new(35.0, 150.0, 3.0));
+
+    camera.render(&world, &LightList::default());
+}
+
+fn frosted_glass() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.2, 0.3, 0.1)))));
+
+    // clear to frosted, left to right, with a light behind to show the blur
+    for column in 0..4 {
+        let roughness = column as f64 / 4.0;
+        let glass = Arc::new(Dielectric::new(1.5).with_roughness(roughness));
+        let center = Point3::new(2.5 * (column as f64 - 1.5), 1.0, 0.0);
+        world.add(Arc::new(Sphere::stationary(center, 1.0, glass)));
+    }
+
+    let mut lights = LightList::default();
+    let lamp = Arc::new(Sphere::stationary(
+        Point3::new(0.0, 3.0, -6.0),
+        1.0,
+        Arc::new(DiffuseLight::new(Color::new(8.0, 7.0, 6.0))),
+    ));
+    world.add(lamp.clone());
+    lights.add(Arc::new(AreaLight::new(lamp)));
+
+    let mut camera = default_camera(Point3::new(0.0, 3.0, 14.0), Point3::new(0.0, 1.0, 0.0));
+    camera.vfov = 30.0;
+
+    camera.render(&world, &lights);
+}
+
+fn colored_glass() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)))));
+
+    // the same tinted glass gets deeper in color the thicker it is
+    let tint = Color::new(0.9, 0.4, 0.3);
+    for (i, radius) in [0.25, 0.5, 1.0, 1.5].iter().enumerate() {
+        let glass = Arc::new(Dielectric::new(1.5).with_transmission_color(tint, 1.0));
+        let center = Point3::new(-5.0 + 3.0 * i as f64, *radius, 0.0);
+        world.add(Arc::new(Sphere::stationary(center, *radius, glass)));
+    }
+
+    let water = Arc::new(Dielectric::new(1.33).with_absorption(Color::new(0.45, 0.09, 0.06)));
+    world.add(Arc::new(Sphere::stationary(Point3::new(0.0, 1.0, -4.0), 1.0, water)));
+
+    let mut camera = default_camera(Point3::new(0.0, 4.0, 12.0), Point3::new(0.0, 0.8, -1.0));
+    camera.vfov = 35.0;
+    camera.background = Arc::new(Sky::new(50.0, 200.0, 2.5));
+
+    camera.render(&world, &LightList::default());
+}
+
+fn principled() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
+
+    // each row sweeps one parameter from 0 to 1, left to right
+    let base_color = Color::new(0.8, 0.3, 0.1);
+    let sweeps: [fn(&mut Principled, f64); 5] = [
+        |material, t| material.metallic = t,
+        |material, t| material.roughness = t,
+        |material, t| { material.sheen = t; material.roughness = 0.8; },
+        |material, t| { material.clearcoat = t; material.roughness = 0.7; },
+        |material, t| { material.transmission = t; material.roughness = 0.1; },
+    ];
+    for (row, sweep) in sweeps.iter().enumerate() {
+        for column in 0..5 {
+            let mut material = Principled::new(base_color);
+            sweep(&mut material, column as f64 / 4.0);
+
+            let center = Point3::new(2.5 * (column as f64 - 2.0), 1.0, -2.5 * (row as f64 - 2.0));
+            world.add(Arc::new(Sphere::stationary(center, 1.0, Arc::new(material))));
+        }
+    }
+
+    let mut camera = default_camera(Point3::new(0.0, 11.0, 18.0), Point3::new(0.0, 0.0, 0.0));
+    camera.vfov = 40.0;
+    camera.background = Arc::new(Sky::new(35.0, 150.0, 3.0));
+
+    camera.render(&world, &LightList::default());
+}
+
+fn coatings() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
+
+    let wood = Arc::new(Lambertian::new(Color::new(0.45, 0.25, 0.1)));
+    let varnish = Coated::new(wood, 1.5).with_absorption(Color::new(0.05, 0.2, 0.5), 0.5);
+    world.add(Arc::new(Sphere::stationary(Point3::new(-3.3, 1.0, 0.0), 1.0, Arc::new(varnish))));
+
+    let paint = Arc::new(Metal::new(Color::new(0.7, 0.05, 0.05), 0.5));
+    let lacquer = Coated::new(paint, 1.5);
+    world.add(Arc::new(Sphere::stationary(Point3::new(-1.1, 1.0, 0.0), 1.0, Arc::new(lacquer))));
+
+    let satin = Coated::new(Arc::new(Lambertian::new(Color::new(0.1, 0.3, 0.6))), 1.5).with_roughness(0.3);
+    world.add(Arc::new(Sphere::stationary(Point3::new(1.1, 1.0, 0.0), 1.0, Arc::new(satin))));
+
+    let coated_gold = Coated::new(Arc::new(Metal::gold(0.0)), 1.5).with_roughness(0.2);
+    world.add(Arc::new(Sphere::stationary(Point3::new(3.3, 1.0, 0.0), 1.0, Arc::new(coated_gold))));
+
+    let mut lights = LightList::default();
+    lights.add(Arc::new(PointLight::new(Point3::new(-2.0, 6.0, 4.0), Color::new(40.0, 40.0, 40.0))));
+
+    let mut camera = default_camera(Point3::new(0.0, 3.0, 14.0), Point3::new(0.0, 1.0, 0.0));
+    camera.vfov = 30.0;
+    camera.background = Arc::new(Sky::new(35.0, 150.0, 3.0));
+
+    camera.render(&world, &lights);
+}
+
+fn rough_diffuse() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(OrenNayar::new(Color::new(0.5, 0.5, 0.5), 30.0))));
+
+    // Lambertian on the left, then rougher and rougher clay
+    let clay = Color::new(0.7, 0.45, 0.3);
+    world.add(Arc::new(Sphere::stationary(Point3::new(-3.3, 1.0, 0.0), 1.0, Arc::new(Lambertian::new(clay)))));
+    for (i, sigma) in [20.0, 40.0, 60.0].iter().enumerate() {
+        let center = Point3::new(-1.1 + 2.2 * i as f64, 1.0, 0.0);
+        world.add(Arc::new(Sphere::stationary(center, 1.0, Arc::new(OrenNayar::new(clay, *sigma)))));
+    }
+
+    // lit from behind the camera, where rough surfaces look flattest
+    let mut lights = LightList::default();
+    lights.add(Arc::new(DirectionalLight::new(Vec3::new(0.2, -0.3, -1.0), Color::new(3.0, 3.0, 3.0))));
+
+    let mut camera = default_camera(Point3::new(0.0, 3.0, 14.0), Point3::new(0.0, 1.0, 0.0));
+    camera.vfov = 30.0;
+    camera.background = Arc::new(Gradient::new(Color::new(0.05, 0.05, 0.05), Color::new(0.1, 0.1, 0.15)));
+
+    camera.render(&world, &lights);
+}
+
+fn iridescence() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.2, 0.2, 0.2)))));
+
+    // soap bubbles: a water film with air on both sides
+    for (i, thickness) in [250.0, 400.0, 550.0].iter().enumerate() {
+        let bubble = Dielectric::new(1.0).with_thin_film(ThinFilm::new(*thickness, 1.33));
+        let center = Point3::new(-3.3 + 2.2 * i as f64, 1.0, 0.0);
+        world.add(Arc::new(Sphere::stationary(center, 1.0, Arc::new(bubble))));
+    }
+
+    // heat-tinted steel, with the oxide thicker in patches
+    let patches = Arc::new(CheckerTexture::from_colors(0.5, Color::new(0.6, 0.6, 0.6), Color::new(1.0, 1.0, 1.0)));
+    let oxide = ThinFilm::textured(patches, 300.0, 2.4);
+    let steel = Metal::new(Color::new(0.55, 0.55, 0.55), 0.2).with_thin_film(oxide);
+    world.add(Arc::new(Sphere::stationary(Point3::new(3.3, 1.0, 0.0), 1.0, Arc::new(steel))));
+
+    let coated_lens = Dielectric::new(1.5).with_thin_film(ThinFilm::new(100.0, 1.38));
+    world.add(Arc::new(Sphere::stationary(Point3::new(0.0, 0.6, 2.5), 0.6, Arc::new(coated_lens))));
+
+    let mut camera = default_camera(Point3::new(0.0, 3.0, 14.0), Point3::new(0.0, 1.0, 0.0));
+    camera.vfov = 30.0;
+    camera.background = Arc::new(Sky::new(35.0, 150.0, 3.0));
+
+    camera.render(&world, &LightList::default());
+}
+
+fn spectral() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
+
+    // gold, from Johnson and Christy's measurements
+    let eta = SampledSpectrum::new(vec![
+        (400.0, 1.658), (450.0, 1.476), (500.0, 0.970), (550.0, 0.427),
+        (600.0, 0.247), (650.0, 0.166), (700.0, 0.161), (750.0, 0.164),
+    ]);
+    let k = SampledSpectrum::new(vec![
+        (400.0, 1.956), (450.0, 1.952), (500.0, 1.870), (550.0, 2.449),
+        (600.0, 3.068), (650.0, 3.630), (700.0, 4.130), (750.0, 4.624),
+    ]);
+    let gold = Metal::measured(eta, k, 0.15);
+    world.add(Arc::new(Sphere::stationary(Point3::new(0.0, 1.0, 0.0), 1.0, Arc::new(gold))));
+
+    let red = Arc::new(Lambertian::new(Color::new(0.7, 0.1, 0.1)));
+    world.add(Arc::new(Sphere::stationary(Point3::new(-2.2, 1.0, 0.0), 1.0, red)));
+
+    let bubble = Dielectric::new(1.0).with_thin_film(ThinFilm::new(400.0, 1.33));
+    world.add(Arc::new(Sphere::stationary(Point3::new(2.2, 1.0, 0.0), 1.0, Arc::new(bubble))));
+
+    let mut camera = default_camera(Point3::new(0.0, 3.0, 12.0), Point3::new(0.0, 1.0, 0.0));
+    camera.vfov = 30.0;
+    camera.background = Arc::new(Sky::new(35.0, 150.0, 3.0));
+    camera.spectral = true;
+
+    camera.render(&world, &LightList::default());
+}
+
+fn dispersion() {
+    let mut world = HittableList::default();
+    let mut lights = LightList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.6, 0.6, 0.6)))));
+
+    let glasses = [Dispersion::bk7(), Dispersion::sf11(), Dispersion::diamond()];
+    for (i, glass) in glasses.into_iter().enumerate() {
+        let center = Point3::new(-2.4 + 2.4 * i as f64, 1.0, 0.0);
+        world.add(Arc::new(Sphere::stationary(center, 1.0, Arc::new(Dielectric::dispersive(glass)))));
+    }
+
+    // a small bright lamp behind the spheres casts rainbow-edged caustics
+    let lamp = Arc::new(Sphere::stationary(
+        Point3::new(0.0, 4.0, -6.0),
+        0.3,
+        Arc::new(DiffuseLight::new(Color::new(200.0, 200.0, 200.0))),
+    ));
+    world.add(lamp.clone());
+    lights.add(Arc::new(AreaLight::new(lamp)));
+
+    let mut camera = default_camera(Point3::new(0.0, 5.0, 12.0), Point3::new(0.0, 0.5, 0.0));
+    camera.vfov = 30.0;
+    camera.background = Arc::new(Gradient::new(Color::new(0.05, 0.05, 0.05), Color::new(0.1, 0.1, 0.15)));
+    camera.spectral = true;
+
+    camera.render(&world, &lights);
+}
+
+fn subsurface() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
+
+    // red light goes deepest into skin, blue the least
+    let materials = [
+        Subsurface::new(Color::new(0.9, 0.9, 0.88), Color::new(0.2, 0.2, 0.2), 1.5).with_roughness(0.2),
+        Subsurface::new(Color::new(0.85, 0.55, 0.45), Color::new(0.4, 0.15, 0.08), 1.4).with_roughness(0.4),
+        Subsurface::new(Color::new(0.95, 0.95, 0.92), Color::new(0.05, 0.05, 0.06), 1.35),
+        Subsurface::new(Color::new(0.9, 0.6, 0.2), Color::new(0.5, 0.3, 0.15), 1.45).with_roughness(0.1),
+    ];
+    for (i, material) in materials.into_iter().enumerate() {
+        let center = Point3::new(-3.3 + 2.2 * i as f64, 1.0, 0.0);
+        world.add(Arc::new(Sphere::stationary(center, 1.0, Arc::new(material))));
+    }
+
+    let mut camera = default_camera(Point3::new(0.0, 3.0, 14.0), Point3::new(0.0, 1.0, 0.0));
+    camera.vfov = 30.0;
+    camera.max_depth = 500;
+    camera.background = Arc::new(Gradient::default());
+
+    camera.render(&world, &LightList::default());
+}
+
+fn normal_mapping() {
+    let mut world = HittableList::default();
+    let mut lights = LightList::default();
+
+    // tiles, with grooves where the heights step between squares
+    let tiles = Arc::new(CheckerTexture::from_colors(1.0, Color::zero(), Color::new(1.0, 1.0, 1.0)));
+    let floor = NormalMapped::bumped(Arc::new(Lambertian::new(Color::new(0.6, 0.55, 0.5))), tiles, 0.02);
+    world.add(Arc::new(Quad::new(
+        Point3::new(-8.0, 0.0, -8.0),
+        Vec3::new(16.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 16.0),
+        Arc::new(floor),
+    )));
+
+    let dimpled = Arc::new(dimples(256, 16));
+    let golf_ball = NormalMapped::new(Arc::new(Lambertian::new(Color::new(0.9, 0.9, 0.9))), dimpled.clone());
+    world.add(Arc::new(Sphere::stationary(Point3::new(-2.2, 1.0, 0.0), 1.0, Arc::new(golf_ball))));
+
+    let hammered = NormalMapped::new(Arc::new(Metal::copper(0.15)), dimpled.clone());
+    world.add(Arc::new(Sphere::stationary(Point3::new(0.0, 1.0, 0.0), 1.0, Arc::new(hammered))));
+
+    let plate = NormalMapped::new(Arc::new(Metal::aluminum(0.2)), dimpled);
+    world.add(Arc::new(Triangle::new(
+        Point3::new(1.4, 0.0, -1.0),
+        Point3::new(3.6, 0.0, -0.5),
+        Point3::new(2.5, 2.2, -0.8),
+        Arc::new(plate),
+    ).with_uvs([(0.0, 0.0), (1.0, 0.0), (0.5, 1.0)])));
+
+    let lamp = Arc::new(Quad::new(
+        Point3::new(-1.0, 5.0, 1.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 2.0),
+        Arc::new(DiffuseLight::new(Color::new(6.0, 6.0, 5.0))),
+    ));
+    world.add(lamp.clone());
+    lights.add(Arc::new(AreaLight::new(lamp)));
+
+    let mut camera = default_camera(Point3::new(0.0, 4.0, 10.0), Point3::new(0.0, 0.8, 0.0));
+    camera.vfov = 30.0;
+    camera.background = Arc::new(Gradient::new(Color::new(0.1, 0.1, 0.1), Color::new(0.3, 0.35, 0.45)));
+
+    camera.render(&world, &lights);
+}
+
+/// Tangent-space normal map of [count] by [count] round dimples, [size] pixels square
+fn dimples(size: usize, count: usize) -> ImageTexture {
+    let mut pixels = Vec::with_capacity(size * size);
+
+    for j in 0..size {
+        for i in 0..size {
+            // position within the dimple, from -1 to 1 across it
+            let cell = |k: usize| ((k as f64 + 0.5) * count as f64 / size as f64).fract() * 2.0 - 1.0;
+            let (x, y) = (cell(i), -cell(j));
+            let r2 = x * x + y * y;
+
+            let n = if r2 < 0.8 { Vec3::new(-0.6 * x, -0.6 * y, 1.0) } else { Vec3::new(0.0, 0.0, 1.0) };
+            let n = n / n.length();
+            pixels.push(0.5 * (n + Color::new(1.0, 1.0, 1.0)));
+        }
+    }
+
+    ImageTexture::from_pixels(size, size, pixels)
+}
+
+fn cutouts() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
+
+    // a fence of crossing bars, casting its pattern in the sun
+    let bars = Arc::new(mask(256, |u, v| (u * 8.0).fract() < 0.15 || (v * 4.0).fract() < 0.15));
+    let wood = Arc::new(Lambertian::new(Color::new(0.45, 0.3, 0.2)));
+    world.add(Arc::new(Quad::new(
+        Point3::new(-4.0, 0.0, -1.5),
+        Vec3::new(8.0, 0.0, 0.0),
+        Vec3::new(0.0, 2.0, 0.0),
+        Arc::new(Cutout::new(wood, bars)),
+    )));
+
+    // leaves, each a quad cut to shape
+    let leaf = Arc::new(mask(128, |u, v| {
+        let (x, y) = ((u - 0.5) / 0.48, (v - 0.5) / 0.22);
+        x * x + y * y < 1.0
+    }));
+    let green = Arc::new(Lambertian::new(Color::new(0.2, 0.5, 0.1)));
+    for i in 0..12 {
+        let angle = i as f64 * 2.4;
+        let corner = Point3::new(2.0 + 0.3 * angle.cos(), 1.0 + 0.1 * i as f64, 1.0 + 0.3 * angle.sin());
+        let along = Vec3::new(angle.cos(), 0.3, angle.sin());
+        let across = Vec3::new(-0.4 * angle.sin(), 0.1, 0.4 * angle.cos());
+        world.add(Arc::new(Quad::new(corner, along, across, Arc::new(Cutout::new(green.clone(), leaf.clone())))));
+    }
+
+    // holes in a sphere let the inside show through
+    let holes = Arc::new(CheckerTexture::from_colors(0.3, Color::zero(), Color::new(1.0, 1.0, 1.0)));
+    let shell = Cutout::new(Arc::new(Metal::gold(0.2)), holes);
+    world.add(Arc::new(Sphere::stationary(Point3::new(-1.8, 1.0, 1.5), 1.0, Arc::new(shell))));
+
+    let mut camera = default_camera(Point3::new(0.0, 3.0, 12.0), Point3::new(0.0, 1.0, 0.0));
+    camera.vfov = 30.0;
+    camera.background = Arc::new(Sky::new(35.0, 150.0, 3.0));
+
+    camera.render(&world, &LightList::default());
+}
+
+/// Opacity mask [size] pixels square, opaque where [inside] holds for the
+/// texture coordinates
+fn mask(size: usize, inside: impl Fn(f64, f64) -> bool) -> ImageTexture {
+    let mut pixels = Vec::with_capacity(size * size);
+
+    for j in 0..size {
+        for i in 0..size {
+            let (u, v) = ((i as f64 + 0.5) / size as f64, 1.0 - (j as f64 + 0.5) / size as f64);
+            let opacity = if inside(u, v) { 1.0 } else { 0.0 };
+            pixels.push(Color::new(opacity, opacity, opacity));
+        }
+    }
+
+    ImageTexture::from_pixels(size, size, pixels)
+}
+
+fn mixes() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
+
+    let blotches = Arc::new(mask(512, |u, v| {
+        (u * 37.0).sin() * (v * 23.0).sin() + 0.5 * (u * 71.0 + v * 13.0).sin() > 0.4
+    }));
+
+    // rust eating into steel
+    let steel = Arc::new(Metal::new(Color::new(0.6, 0.6, 0.6), 0.1));
+    let rust = Arc::new(OrenNayar::new(Color::new(0.45, 0.2, 0.08), 20.0));
+    let rusty = MixMaterial::new(steel, rust, blotches.clone());
+    world.add(Arc::new(Sphere::stationary(Point3::new(-2.2, 1.0, 0.0), 1.0, Arc::new(rusty))));
+
+    // dirt on paint
+    let paint = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5)));
+    let dirt = Arc::new(Lambertian::new(Color::new(0.3, 0.25, 0.15)));
+    world.add(Arc::new(Sphere::stationary(Point3::new(0.0, 1.0, 0.0), 1.0, Arc::new(MixMaterial::new(paint, dirt, blotches)))));
+
+    // plastic: a diffuse body under a reflection that grows at grazing angles
+    let body = Arc::new(Lambertian::new(Color::new(0.7, 0.1, 0.1)));
+    let gloss = Arc::new(Metal::new(Color::new(1.0, 1.0, 1.0), 0.05));
+    world.add(Arc::new(Sphere::stationary(Point3::new(2.2, 1.0, 0.0), 1.0, Arc::new(MixMaterial::fresnel(body, gloss, 1.5)))));
+
+    let mut camera = default_camera(Point3::new(0.0, 3.0, 12.0), Point3::new(0.0, 1.0, 0.0));
+    camera.vfov = 30.0;
+    camera.background = Arc::new(Sky::new(35.0, 150.0, 3.0));
+
+    camera.render(&world, &LightList::default());
+}
+
+fn two_sided() {
+    let mut world = HittableList::default();
+    let mut lights = LightList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
+
+    // paper printed with stripes on the front and dots on the back
+    let paper = Arc::new(Lambertian::new(Color::new(0.85, 0.85, 0.8)));
+    let stripes = Arc::new(mask(256, |u, _| (u * 6.0).fract() < 0.5));
+    let dots = Arc::new(mask(256, |u, v| {
+        let (x, y) = ((u * 5.0).fract() - 0.5, (v * 5.0).fract() - 0.5);
+        x * x + y * y < 0.1
+    }));
+    let front = MixMaterial::new(paper.clone(), Arc::new(Lambertian::new(Color::new(0.7, 0.1, 0.1))), stripes);
+    let back = MixMaterial::new(paper, Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.7))), dots);
+    let printed = Arc::new(TwoSided::new(Arc::new(front), Arc::new(back)));
+
+    // the first sheet faces the camera, the second faces away
+    world.add(Arc::new(Quad::new(
+        Point3::new(-2.6, 0.2, 0.0),
+        Vec3::new(1.6, 0.0, 0.0),
+        Vec3::new(0.0, 2.0, 0.0),
+        printed.clone(),
+    )));
+    world.add(Arc::new(Quad::new(
+        Point3::new(2.6, 0.2, 0.0),
+        Vec3::new(-1.6, 0.0, 0.0),
+        Vec3::new(0.0, 2.0, 0.0),
+        printed,
+    )));
+
+    // a panel light shining down only, dark seen from above
+    let panel = Arc::new(Quad::new(
+        Point3::new(-1.0, 3.5, -0.5),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 2.0),
+        Arc::new(DiffuseLight::one_sided(Color::new(8.0, 8.0, 7.0))),
+    ));
+    world.add(panel.clone());
+    lights.add(Arc::new(AreaLight::new(panel)));
+
+    let mut camera = default_camera(Point3::new(0.0, 6.0, 10.0), Point3::new(0.0, 1.2, 0.0));
+    camera.vfov = 35.0;
+    camera.background = Arc::new(Gradient::new(Color::new(0.05, 0.05, 0.05), Color::new(0.1, 0.1, 0.15)));
+
+    camera.render(&world, &lights);
+}
+
+fn isometric() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))));
+
+    // a staircase of blocks next to a column
+    let stone = Arc::new(Lambertian::new(Color::new(0.7, 0.6, 0.45)));
+    for step in 0..4 {
+        let x = step as f64;
+        let top = x + 1.0;
+        world.add(Arc::new(block(Point3::new(x - 2.0, 0.0, -1.5), Point3::new(x - 1.0, top, 1.5), stone.clone())));
+    }
+
+    let marble = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)));
+    world.add(Arc::new(block(Point3::new(-3.5, 0.0, 2.0), Point3::new(-2.5, 3.0, 3.0), marble)));
+    world.add(Arc::new(Sphere::stationary(Point3::new(-3.0, 3.5, 2.5), 0.5, Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.1)))));
+
+    let mut camera = default_camera(Point3::new(10.0, 11.5, 10.0), Point3::new(0.0, 1.5, 0.0));
+    camera.background = Arc::new(Sky::new(35.0, 150.0, 3.0));
+
+    // equal foreshortening of all three axes
+    camera.projection = Projection::Orthographic { width: 12.0 };
+
+    camera.render(&world, &LightList::default());
+}
+
+/// Axis-aligned box with opposite corners [a] and [b], made of six quads
+fn block(a: Point3, b: Point3, mat: Arc<dyn Material>) -> HittableList {
+    let mut sides = HittableList::default();
+
+    let min = Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+    let max = Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+
+    let dx = Vec3::new(max.x() - min.x(), 0.0, 0.0);
+    let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
+    let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
+
+    sides.add(Arc::new(Quad::new(Point3::new(min.x(), min.y(), max.z()), dx, dy, mat.clone()))); // front
+    sides.add(Arc::new(Quad::new(Point3::new(max.x(), min.y(), max.z()), -dz, dy, mat.clone()))); // right
+    sides.add(Arc::new(Quad::new(Point3::new(max.x(), min.y(), min.z()), -dx, dy, mat.clone()))); // back
+    sides.add(Arc::new(Quad::new(Point3::new(min.x(), min.y(), min.z()), dz, dy, mat.clone()))); // left
+    sides.add(Arc::new(Quad::new(Point3::new(min.x(), max.y(), max.z()), dx, -dz, mat.clone()))); // top
+    sides.add(Arc::new(Quad::new(Point3::new(min.x(), min.y(), min.z()), dx, dz, mat))); // bottom
+
+    sides
+}
+
+fn panorama(projection: Projection, stereo: Option<Stereo>) {
+    let mut world = HittableList::default();
+
+    let checker = Arc::new(CheckerTexture::from_colors(0.5, Color::zero(), Color::new(1.0, 1.0, 1.0)));
+    let grass = Arc::new(Lambertian::new(Color::new(0.2, 0.3, 0.1)));
+    let tiles = Arc::new(Lambertian::new(Color::new(0.9, 0.9, 0.9)));
+    world.add(ground(Arc::new(MixMaterial::new(grass, tiles, checker))));
+
+    // a ring of spheres all around the camera
+    let count = 12;
+    for k in 0..count {
+        let angle = 2.0 * PI * (k as f64) / (count as f64);
+        let center = Point3::new(4.0 * angle.cos(), 1.0, 4.0 * angle.sin());
+        let albedo = Color::new(0.5 + 0.5 * angle.cos(), 0.5 + 0.5 * (angle + 2.1).cos(), 0.5 + 0.5 * (angle + 4.2).cos());
+
+        let mat: Arc<dyn Material> = if k % 3 == 0 {
+            Arc::new(Metal::new(albedo, 0.05))
+        } else {
+            Arc::new(Lambertian::new(albedo))
+        };
+        world.add(Arc::new(Sphere::stationary(center, 1.0, mat)));
+    }
+
+    // a fisheye looks up at the sky, the panorama straight ahead
+    let (lookat, vup) = match projection {
+        Projection::Fisheye { .. } => (Point3::new(0.0, 2.0, 0.0), Vec3::new(0.0, 0.0, -1.0)),
+        _ => (Point3::new(0.0, 1.0, -1.0), Vec3::new(0.0, 1.0, 0.0)),
+    };
+
+    let mut camera = default_camera(Point3::new(0.0, 1.0, 0.0), lookat);
+    camera.vup = vup;
+    camera.aspect_ratio = match projection {
+        Projection::Equirectangular => 2.0,
+        _ => 1.0,
+    };
+    camera.background = Arc::new(Sky::new(35.0, 150.0, 3.0));
+    camera.projection = projection;
+    camera.stereo = stereo;
+
+    camera.render(&world, &LightList::default());
+}
+
+fn bokeh(aperture: Aperture, anamorphic_squeeze: f64) {
+    let mut world = HittableList::default();
+    let mut lights = LightList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.3, 0.3, 0.3)))));
+
+    // in focus
+    let subject = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.2));
+    world.add(Arc::new(Sphere::stationary(Point3::new(0.0, 0.5, 0.0), 0.5, subject)));
+
+    // far behind, small lights blur into the shape of the aperture
+    for k in 0..40 {
+        let center = Point3::new(random_f64_within(-12.0, 12.0), random_f64_within(1.0, 8.0), random_f64_within(-40.0, -25.0));
+        let glow = Color::new(random_f64_within(20.0, 60.0), random_f64_within(15.0, 40.0), random_f64_within(5.0, 20.0));
+        let size = if k % 4 == 0 { 0.2 } else { 0.12 };
+        world.add(Arc::new(Sphere::stationary(center, size, Arc::new(DiffuseLight::new(glow)))));
+    }
+
+    lights.add(Arc::new(DirectionalLight::new(Vec3::new(-1.0, -1.0, -1.0), Color::new(2.0, 2.0, 2.0))));
+
+    let mut camera = default_camera(Point3::new(0.0, 1.0, 5.0), Point3::new(0.0, 0.5, 0.0));
+    camera.vfov = 30.0;
+    camera.samples_per_pixel = 200;
+    camera.background = Arc::new(Gradient::new(Color::new(0.01, 0.01, 0.02), Color::new(0.02, 0.02, 0.04)));
+
+    camera.defocus_angle = 2.5;
+    camera.focus_dist = 5.0;
+    camera.aperture = aperture;
+    camera.anamorphic_squeeze = anamorphic_squeeze;
+
+    camera.render(&world, &lights);
+}
+
+fn real_lens(path: &str) {
+    let lens_system = match LensSystem::load(path) {
+        Ok(lens_system) => lens_system,
+        Err(e) => {
+            eprintln!("Unable to load lens prescription {}: {}", path, e);
+            return;
+        }
+    };
+
+    let mut world = HittableList::default();
+
+    let checker = Arc::new(CheckerTexture::from_colors(0.25, Color::zero(), Color::new(1.0, 1.0, 1.0)));
+    let dark = Arc::new(Lambertian::new(Color::new(0.1, 0.1, 0.1)));
+    let light = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)));
+    world.add(ground(Arc::new(MixMaterial::new(dark, light, checker))));
+
+    // a row of spheres running away from the camera, a meter apart
+    for k in 0..8 {
+        let z = -(k as f64);
+        let albedo = Color::new(0.8, 0.3 + 0.08 * k as f64, 0.2);
+        world.add(Arc::new(Sphere::stationary(Point3::new(0.6, 0.25, z), 0.25, Arc::new(Lambertian::new(albedo)))));
+        world.add(Arc::new(Sphere::stationary(Point3::new(-0.6, 0.25, z), 0.25, Arc::new(Metal::new(albedo, 0.1)))));
+    }
+
+    let mut camera = default_camera(Point3::new(0.0, 0.8, 3.0), Point3::new(0.0, 0.25, -2.0));
+    camera.aspect_ratio = 3.0 / 2.0;
+    camera.background = Arc::new(Sky::new(35.0, 150.0, 3.0));
+
+    // in meters, with the lens focused on the third pair of spheres
+    camera.lens_system = Some(lens_system);
+    camera.focus_dist = 5.0;
+
+    camera.render(&world, &LightList::default());
+}
+
+fn architecture() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.4, 0.4, 0.4)))));
+
+    // towers well above the eye level of the camera
+    let concrete = Arc::new(Lambertian::new(Color::new(0.7, 0.68, 0.65)));
+    let glass = Arc::new(Metal::new(Color::new(0.5, 0.6, 0.7), 0.05));
+    world.add(Arc::new(block(Point3::new(-9.0, 0.0, -20.0), Point3::new(-3.0, 24.0, -14.0), concrete.clone())));
+    world.add(Arc::new(block(Point3::new(-2.0, 0.0, -26.0), Point3::new(4.0, 34.0, -20.0), glass)));
+    world.add(Arc::new(block(Point3::new(5.0, 0.0, -18.0), Point3::new(10.0, 16.0, -13.0), concrete)));
+
+    let mut camera = default_camera(Point3::new(0.0, 1.6, 30.0), Point3::new(0.0, 1.6, 0.0));
+    camera.vfov = 60.0;
+    camera.aspect_ratio = 2.0 / 3.0;
+    camera.image_width = 300;
+    camera.background = Arc::new(Sky::new(35.0, 150.0, 3.0));
+
+    // looking level keeps the verticals parallel, the shift brings the tops in
+    camera.shift_y = 0.4;
+
+    camera.render(&world, &LightList::default());
+}
+
+fn miniature() {
+    let mut world = HittableList::default();
+
+    world.add(ground(Arc::new(Lambertian::new(Color::new(0.35, 0.5, 0.3)))));
+
+    for a in -6..7 {
+        for b in -12..4 {
+            let center = Point3::new(1.5 * a as f64, 0.3, 1.5 * b as f64);
+            let albedo = Color::random() * Color::random();
+            world.add(Arc::new(Sphere::stationary(center, 0.3, Arc::new(Lambertian::new(albedo)))));
+        }
+    }
+
+    let mut camera = default_camera(Point3::new(0.0, 8.0, 12.0), Point3::new(0.0, 0.0, -3.0));
+    camera.vfov = 40.0;
+    camera.background = Arc::new(Sky::new(35.0, 150.0, 3.0));
+
+    // leaning the plane of focus against the ground leaves a thin band sharp,
+    // which makes the scene look like a scale model
+    camera.defocus_angle = 3.0;
+    camera.focus_dist = 17.0;
+    camera.focus_tilt = -45.0;
+
+    camera.render(&world, &LightList::default());
+}
+
+fn portrait() {
+    let mut world = HittableList::default();
+
+    let checker = Arc::new(CheckerTexture::from_colors(0.2, Color::zero(), Color::new(1.0, 1.0, 1.0)));
+    let dark = Arc::new(Lambertian::new(Color::new(0.2, 0.2, 0.2)));
+    let light = Arc::new(Lambertian::new(Color::new(0.7, 0.7, 0.7)));
+    world.add(ground(Arc::new(MixMaterial::new(dark, light, checker))));
+
+    // in meters: one sphere in front of the subject and one behind it
+    world.add(Arc::new(Sphere::stationary(Point3::new(-0.5, 0.3, 1.5), 0.3, Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5))))));
+    world.add(Arc::new(Sphere::stationary(Point3::new(0.0, 0.3, 0.0), 0.3, Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.1)))));
+    world.add(Arc::new(Sphere::stationary(Point3::new(0.6, 0.3, -2.5), 0.3, Arc::new(Lambertian::new(Color::new(0.6, 0.1, 0.1))))));
+
+    let mut camera = default_camera(Point3::new(0.0, 0.6, 4.0), Point3::new(0.0, 0.3, 0.0));
+    camera.aspect_ratio = 3.0 / 2.0;
+    camera.background = Arc::new(Sky::new(35.0, 150.0, 3.0));
+
+    // a 56 mm lens wide open on an APS-C sensor frames like an 85 mm
+    // portrait lens on full frame, focused on the sphere in the middle
+    camera.lens_settings = Some(LensSettings::new(56.0, 1.4).with_sensor(23.5, 15.6));
+    camera.autofocus = Some((200, 133));
+
+    camera.render(&world, &LightList::default());
 }
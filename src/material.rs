@@ -1,11 +1,78 @@
-use crate::color::Color;
+use std::f64::consts::PI;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+use crate::color::{luminance, Color};
+use crate::dispersion::Dispersion;
+use crate::fresnel::{fr_complex, fr_dielectric, Complex};
 use crate::hittable::HitRecord;
+use crate::microfacet::TrowbridgeReitz;
+use crate::onb::Onb;
 use crate::ray::Ray;
 use crate::rt_weekend::random_f64;
-use crate::vec3::{dot, random_unit_vector, reflect, refract, unit_vector, Vec3};
+use crate::spectrum::{along_ray, evaluate_along, rgb_at, SampledSpectrum, SingleWavelength};
+use crate::texture::Texture;
+use crate::thin_film::ThinFilm;
+use crate::vec3::{dot, random_unit_vector, reflect, refract, unit_vector, Point3, Vec3};
 
-pub trait Material {
+/// Times light may bounce between the base of a [Coated] material and the
+/// underside of its coating before it is given up on
+const MAX_COATING_BOUNCES: i32 = 16;
+
+pub trait Material: Send + Sync {
+    /// In spectral mode, when [r_in] carries wavelengths, [attenuation] holds
+    /// values at those wavelengths, as do the colors [eval] returns
     fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool;
+
+    /// Scattering function times the cosine term, for light arriving from
+    /// `direction` and leaving back along `r_in`.
+    /// NB: materials that only scatter specularly keep the default of zero
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Vec3) -> Color {
+        Color::zero()
+    }
+
+    /// Solid angle density with which `scatter` picks `direction`.
+    /// Zero tells the renderer that it can't weigh light samples against
+    /// this material's own choices
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Radiance given off by the surface back along [r_in], in RGB even in
+    /// spectral mode; the camera upsamples it like lights and backgrounds
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord) -> Color {
+        Color::zero()
+    }
+
+    /// Chance that the surface is there at all at the given surface
+    /// coordinates, less than one for cutouts like leaves. See [is_cut_out]
+    fn opacity(&self, _u: f64, _v: f64, _p: &Point3) -> f64 {
+        1.0
+    }
+}
+
+/// Whether a hit of [r] on [mat] at the given surface coordinates falls in
+/// a cutout, so that hittables skip it and rays go on to whatever is behind.
+/// Decided by a hash of the ray and the hit rather than at random, so that
+/// intersecting the same ray again gives the same answer
+pub fn is_cut_out(mat: &dyn Material, r: &Ray, u: f64, v: f64, p: &Point3) -> bool {
+    let opacity = mat.opacity(u, v, p);
+    if opacity >= 1.0 {
+        return false;
+    }
+
+    let (origin, direction) = (r.origin(), r.direction());
+    hashed_random([origin.x(), origin.y(), origin.z(), direction.x(), direction.y(), direction.z(), u, v]) >= opacity
+}
+
+/// Number in [0,1) that stays the same for the same [values], standing in
+/// for a random one where repeating a decision must give the same result
+fn hashed_random<const N: usize>(values: [f64; N]) -> f64 {
+    let mut hasher = DefaultHasher::new();
+    for x in values {
+        x.to_bits().hash(&mut hasher);
+    }
+
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
 }
 
 /// Lambertian Material
@@ -29,38 +96,301 @@ impl Material for Lambertian {
         }
 
         *scattered = Ray::timed(rec.p, scatter_direction, r_in.time());
-        *attenuation = self.albedo;
+        *attenuation = along_ray(r_in, self.albedo);
         true
     }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let cosine = dot(unit_vector(direction), rec.normal);
+        if cosine <= 0.0 { Color::zero() } else { along_ray(r_in, self.albedo) * (cosine / PI) }
+    }
+
+    /// Directions are cosine distributed around the normal
+    fn pdf(&self, _r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        dot(unit_vector(direction), rec.normal).max(0.0) / PI
+    }
 }
 
-/// Metal (with fuzz factor)
-pub struct Metal {
+/// Oren-Nayar rough diffuse material, which scatters more light back
+/// toward where it came from than [Lambertian], as clay or concrete do
+pub struct OrenNayar {
     albedo: Color,
-    fuzz: f64,
+    a: f64,
+    b: f64,
 }
 
-impl Metal {
-    pub fn new(albedo: Color, fuzz: f64) -> Self {
+impl OrenNayar {
+    /// [sigma] is the standard deviation of the microfacet slope angle, in
+    /// degrees; 0 is Lambertian
+    pub fn new(albedo: Color, sigma: f64) -> Self {
+        let sigma2 = sigma.to_radians().powi(2);
         Self {
             albedo,
-            fuzz: if fuzz < 1.0 { fuzz } else { 1.0 },
+            a: 1.0 - sigma2 / (2.0 * (sigma2 + 0.33)),
+            b: 0.45 * sigma2 / (sigma2 + 0.09),
+        }
+    }
+
+    /// Scattering function relative to Lambertian, in the shading frame
+    fn scale(&self, wo: Vec3, wi: Vec3) -> f64 {
+        let sin_theta_o = (1.0 - wo.z() * wo.z()).max(0.0).sqrt();
+        let sin_theta_i = (1.0 - wi.z() * wi.z()).max(0.0).sqrt();
+        if sin_theta_o < 1e-4 || sin_theta_i < 1e-4 {
+            return self.a;
+        }
+
+        // cosine of the azimuth between the two directions
+        let cos_phi = ((wo.x() * wi.x() + wo.y() * wi.y()) / (sin_theta_o * sin_theta_i)).max(0.0);
+
+        let (sin_alpha, tan_beta) = if wi.z() < wo.z() {
+            (sin_theta_i, sin_theta_o / wo.z())
+        } else {
+            (sin_theta_o, sin_theta_i / wi.z())
+        };
+
+        self.a + self.b * cos_phi * sin_alpha * tan_beta
+    }
+}
+
+impl Material for OrenNayar {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
+        let frame = shading_frame(rec);
+        let wo = frame.to_local(-unit_vector(*r_in.direction()));
+
+        let mut scatter_direction = rec.normal + random_unit_vector();
+
+        // catch degenerate scatter direction
+        if scatter_direction.near_zero() {
+            scatter_direction = rec.normal;
+        }
+        let wi = frame.to_local(unit_vector(scatter_direction));
+
+        *scattered = Ray::timed(rec.p, scatter_direction, r_in.time());
+        *attenuation = along_ray(r_in, self.albedo) * self.scale(wo, wi);
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let frame = shading_frame(rec);
+        let wo = frame.to_local(-unit_vector(*r_in.direction()));
+        let wi = frame.to_local(unit_vector(direction));
+        if wi.z() <= 0.0 {
+            return Color::zero();
+        }
+
+        along_ray(r_in, self.albedo) * (self.scale(wo, wi) * wi.z() / PI)
+    }
+
+    /// Directions are cosine distributed around the normal
+    fn pdf(&self, _r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        dot(unit_vector(direction), rec.normal).max(0.0) / PI
+    }
+}
+
+fn average(c: Color) -> f64 {
+    (c.x() + c.y() + c.z()) / 3.0
+}
+
+/// [r] going on with the one wavelength a path kept, if it did
+fn carry(single: &Option<SingleWavelength>, r: Ray) -> Ray {
+    match single {
+        Some(single) => single.carry(r),
+        None => r,
+    }
+}
+
+/// Local frame for microfacet and other direction-dependent models,
+/// with the shading normal as +z and x along the surface's u direction
+pub fn shading_frame(rec: &HitRecord) -> Onb {
+    Onb::with_tangent(rec.normal, rec.dpdu)
+}
+
+/// How much light a metal reflects depending on the angle of incidence
+enum ConductorFresnel {
+    /// Schlick's approximation from the reflectance at normal incidence
+    Schlick(Color),
+    /// exact, from the complex index of refraction eta + ik
+    Complex { eta: Color, k: Color },
+    /// exact, from measured spectral data
+    Measured { eta: SampledSpectrum, k: SampledSpectrum },
+}
+
+impl ConductorFresnel {
+    fn evaluate(&self, cos_theta: f64, lambda: f64) -> f64 {
+        match self {
+            ConductorFresnel::Schlick(f0) => {
+                let f0 = rgb_at(*f0, lambda);
+                f0 + (1.0 - f0) * (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+            }
+            _ => {
+                let eta = self.complex_ior(lambda);
+                fr_complex(cos_theta, eta.re, eta.im)
+            }
+        }
+    }
+
+    /// Complex index of refraction at [lambda], estimated from the
+    /// reflectance at normal incidence when that's all there is
+    fn complex_ior(&self, lambda: f64) -> Complex {
+        match self {
+            ConductorFresnel::Schlick(f0) => {
+                let r = rgb_at(*f0, lambda).clamp(0.0, 0.999).sqrt();
+                Complex::real((1.0 + r) / (1.0 - r))
+            }
+            ConductorFresnel::Complex { eta, k } => Complex::new(rgb_at(*eta, lambda), rgb_at(*k, lambda)),
+            ConductorFresnel::Measured { eta, k } => Complex::new(eta.value(lambda), k.value(lambda)),
+        }
+    }
+}
+
+/// Metal (GGX microfacet conductor)
+pub struct Metal {
+    fresnel: ConductorFresnel,
+    distribution: TrowbridgeReitz,
+    film: Option<ThinFilm>,
+}
+
+impl Metal {
+    /// [albedo] is the reflectance at normal incidence, and [roughness]
+    /// in [0,1] blurs reflections; 0 is a perfect mirror
+    pub fn new(albedo: Color, roughness: f64) -> Self {
+        Self {
+            fresnel: ConductorFresnel::Schlick(albedo),
+            distribution: TrowbridgeReitz::from_roughness(roughness, roughness),
+            film: None,
         }
     }
 
     pub fn shiny(albedo: Color) -> Self {
-        Self { albedo, fuzz: 0.0 }
+        Self::new(albedo, 0.0)
+    }
+
+    /// Conductor with complex index of refraction [eta] + i[k],
+    /// given for red, green and blue wavelengths
+    pub fn conductor(eta: Color, k: Color, roughness: f64) -> Self {
+        Self {
+            fresnel: ConductorFresnel::Complex { eta, k },
+            distribution: TrowbridgeReitz::from_roughness(roughness, roughness),
+            film: None,
+        }
+    }
+
+    /// Conductor from measured [eta] and [k] spectra, which spectral mode
+    /// follows at every wavelength
+    pub fn measured(eta: SampledSpectrum, k: SampledSpectrum, roughness: f64) -> Self {
+        Self {
+            fresnel: ConductorFresnel::Measured { eta, k },
+            distribution: TrowbridgeReitz::from_roughness(roughness, roughness),
+            film: None,
+        }
+    }
+
+    pub fn gold(roughness: f64) -> Self {
+        Self::conductor(Color::new(0.143, 0.374, 1.442), Color::new(3.983, 2.385, 1.603), roughness)
+    }
+
+    pub fn copper(roughness: f64) -> Self {
+        Self::conductor(Color::new(0.200, 0.924, 1.102), Color::new(3.912, 2.452, 2.142), roughness)
+    }
+
+    pub fn aluminum(roughness: f64) -> Self {
+        Self::conductor(Color::new(1.657, 0.880, 0.521), Color::new(9.224, 6.270, 4.837), roughness)
+    }
+
+    pub fn silver(roughness: f64) -> Self {
+        Self::conductor(Color::new(0.155, 0.117, 0.138), Color::new(4.828, 3.122, 2.147), roughness)
+    }
+
+    /// Brushed look: separate roughness along the two tangent directions
+    pub fn with_anisotropic_roughness(mut self, roughness_u: f64, roughness_v: f64) -> Self {
+        self.distribution = TrowbridgeReitz::from_roughness(roughness_u, roughness_v);
+        self
+    }
+
+    /// Iridescent oxide or coating layer on top of the metal
+    pub fn with_thin_film(mut self, film: ThinFilm) -> Self {
+        self.film = Some(film);
+        self
+    }
+
+    fn reflectance(&self, r_in: &Ray, rec: &HitRecord, cos_theta: f64) -> Color {
+        evaluate_along(r_in, |lambda| match &self.film {
+            Some(film) => film.reflectance(rec, cos_theta, 1.0, self.fresnel.complex_ior(lambda), lambda),
+            None => self.fresnel.evaluate(cos_theta, lambda),
+        })
+    }
+
+    /// Outgoing and incoming directions in the shading frame,
+    /// when both are above the surface
+    fn local_directions(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Vec3, Vec3)> {
+        let frame = shading_frame(rec);
+        let wo = frame.to_local(-unit_vector(*r_in.direction()));
+        let wi = frame.to_local(unit_vector(direction));
+
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return None;
+        }
+
+        Some((wo, wi))
     }
 }
 
 impl Material for Metal {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
-        let mut reflected = reflect(*r_in.direction(), rec.normal);
-        reflected = unit_vector(reflected) + (self.fuzz * random_unit_vector());
-        *scattered = Ray::new(rec.p, reflected);
-        *attenuation = self.albedo;
+        let frame = shading_frame(rec);
+        let wo = frame.to_local(-unit_vector(*r_in.direction()));
+        if wo.z() <= 0.0 {
+            return false;
+        }
+
+        if self.distribution.effectively_smooth() {
+            let reflected = reflect(unit_vector(*r_in.direction()), rec.normal);
+            *scattered = Ray::timed(rec.p, reflected, r_in.time());
+            *attenuation = self.reflectance(r_in, rec, wo.z());
+            return true;
+        }
+
+        // reflect off a microfacet visible from the incoming direction
+        let wm = self.distribution.sample_wm(wo);
+        let wi = reflect(-wo, wm);
+        if wi.z() <= 0.0 {
+            return false;
+        }
+
+        *scattered = Ray::timed(rec.p, frame.transform(wi), r_in.time());
+        *attenuation = self.reflectance(r_in, rec, dot(wo, wm)) * (self.distribution.g(wo, wi) / self.distribution.g1(wo));
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        if self.distribution.effectively_smooth() {
+            return Color::zero();
+        }
+
+        let (wo, wi) = match self.local_directions(r_in, rec, direction) {
+            Some(directions) => directions,
+            None => return Color::zero(),
+        };
+        let wm = unit_vector(wo + wi);
 
-        dot(*scattered.direction(), rec.normal) > 0.0
+        let d = self.distribution.d(wm);
+        let g = self.distribution.g(wo, wi);
+        self.reflectance(r_in, rec, dot(wo, wm)) * (d * g / (4.0 * wo.z()))
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        if self.distribution.effectively_smooth() {
+            return 0.0;
+        }
+
+        let (wo, wi) = match self.local_directions(r_in, rec, direction) {
+            Some(directions) => directions,
+            None => return 0.0,
+        };
+        let wm = unit_vector(wo + wi);
+
+        // change of variables from microfacet normal to reflected direction
+        self.distribution.pdf(wo, wm) / (4.0 * dot(wo, wm).abs())
     }
 }
 
@@ -69,11 +399,114 @@ pub struct Dielectric {
     /// Refractive index in a vacuum or air, or the ratio of the material's
     /// refractive index over the refractive index of the enclosing medium
     refraction_index: f64,
+    /// microfacet roughness, smooth unless set
+    distribution: TrowbridgeReitz,
+    /// absorption coefficient for light travelling inside, per unit
+    /// distance and color channel
+    absorption: Color,
+    film: Option<ThinFilm>,
+    /// index of refraction by wavelength, in place of [refraction_index]
+    dispersion: Option<Dispersion>,
 }
 
 impl Dielectric {
     pub fn new(refraction_index: f64) -> Self {
-        Dielectric { refraction_index }
+        Dielectric {
+            refraction_index,
+            distribution: TrowbridgeReitz::new(0.0, 0.0),
+            absorption: Color::zero(),
+            film: None,
+            dispersion: None,
+        }
+    }
+
+    /// Prism or gemstone look: refracts each wavelength its own way
+    pub fn dispersive(dispersion: Dispersion) -> Self {
+        // the yellow helium line, where glass catalogs quote their index
+        let mut dielectric = Dielectric::new(dispersion.refraction_index(587.6));
+        dielectric.dispersion = Some(dispersion);
+        dielectric
+    }
+
+    /// Colored glass or liquid, absorbing light per unit distance
+    /// travelled inside with coefficient [absorption] (Beer-Lambert)
+    pub fn with_absorption(mut self, absorption: Color) -> Self {
+        self.absorption = absorption;
+        self
+    }
+
+    /// Colored glass or liquid that lets through [color] of the light
+    /// after travelling [distance] inside. A [distance] of zero or less
+    /// is taken as a tiny one, which absorbs almost everything
+    pub fn with_transmission_color(self, color: Color, distance: f64) -> Self {
+        let distance = distance.max(1e-6);
+        let absorption = |c: f64| -c.clamp(1e-6, 1.0).ln() / distance;
+        self.with_absorption(Color::new(absorption(color.x()), absorption(color.y()), absorption(color.z())))
+    }
+
+    /// Frosted look: [roughness] in [0,1] spreads out both the reflected
+    /// and the refracted light
+    pub fn with_roughness(mut self, roughness: f64) -> Self {
+        self.distribution = TrowbridgeReitz::from_roughness(roughness, roughness);
+        self
+    }
+
+    /// Soap bubble or coated lens look: a film on the surface colors what
+    /// it reflects, and what it lets through in the complementary colors
+    pub fn with_thin_film(mut self, film: ThinFilm) -> Self {
+        self.film = Some(film);
+        self
+    }
+
+    /// Index of refraction of the side light goes into over the side it comes from
+    fn relative_eta(&self, rec: &HitRecord) -> f64 {
+        if rec.front_face { self.refraction_index } else { 1.0 / self.refraction_index }
+    }
+
+    /// [relative_eta] for light of [lambda] nanometers
+    fn relative_eta_at(&self, rec: &HitRecord, lambda: f64) -> f64 {
+        let refraction_index = match &self.dispersion {
+            Some(dispersion) => dispersion.refraction_index(lambda),
+            None => self.refraction_index,
+        };
+
+        if rec.front_face { refraction_index } else { 1.0 / refraction_index }
+    }
+
+    /// Relative eta for a refraction, along with the one wavelength it
+    /// follows when dispersion sends each its own way
+    fn disperse(&self, r_in: &Ray, rec: &HitRecord) -> (f64, Option<SingleWavelength>) {
+        match &self.dispersion {
+            Some(_) => {
+                let single = SingleWavelength::along(r_in);
+                (self.relative_eta_at(rec, single.lambda), Some(single))
+            }
+            None => (self.relative_eta(rec), None),
+        }
+    }
+
+    /// Exact Fresnel reflectance at [cos_theta] from the incident side,
+    /// colored by the film if there is one
+    fn fresnel(&self, r_in: &Ray, rec: &HitRecord, cos_theta: f64) -> Color {
+        match &self.film {
+            Some(film) => evaluate_along(r_in, |lambda| {
+                let eta = self.relative_eta_at(rec, lambda);
+                let (eta_i, eta_t) = if rec.front_face { (1.0, eta) } else { (1.0 / eta, 1.0) };
+                film.reflectance(rec, cos_theta, eta_i, Complex::real(eta_t), lambda)
+            }),
+            None => evaluate_along(r_in, |lambda| fr_dielectric(cos_theta, self.relative_eta_at(rec, lambda))),
+        }
+    }
+
+    /// Fraction of the light that made it through the inside along [r_in],
+    /// when it hits the surface from within
+    fn transmittance(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        if rec.front_face {
+            return Color::new(1.0, 1.0, 1.0);
+        }
+
+        let distance = rec.t * r_in.direction().length();
+        evaluate_along(r_in, |lambda| (-rgb_at(self.absorption, lambda) * distance).exp())
     }
 
     fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
@@ -82,14 +515,104 @@ impl Dielectric {
 
         r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
     }
+
+    /// Reflects or refracts through a microfacet visible from the incoming
+    /// direction, choosing between the two by the Fresnel equations
+    fn scatter_rough(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
+        let frame = shading_frame(rec);
+        let wo = frame.to_local(-unit_vector(*r_in.direction()));
+        if wo.z() <= 0.0 {
+            return false;
+        }
+
+        let wm = self.distribution.sample_wm(wo);
+
+        // pick by the average reflectance, and let the weight carry its color
+        let reflectance = self.fresnel(r_in, rec, dot(wo, wm));
+        let reflect_probability = average(reflectance);
+        let mut single = None;
+        let (wi, fresnel_weight) = if random_f64() < reflect_probability {
+            (reflect(-wo, wm), reflectance / reflect_probability)
+        } else {
+            let (etap, refracted) = self.disperse(r_in, rec);
+            let transmitted = (Color::new(1.0, 1.0, 1.0) - reflectance) / (1.0 - reflect_probability);
+            let weight = refracted.as_ref().map_or(Color::new(1.0, 1.0, 1.0), |single| single.weight);
+            single = refracted;
+            (refract(-wo, wm, 1.0 / etap), weight * transmitted)
+        };
+
+        // reflections must stay above the surface, refractions go below
+        let reflected = dot(wo, wm) > 0.0 && wi.z() > 0.0 && dot(wi, wm) > 0.0;
+        let refracted = wi.z() < 0.0 && dot(wi, wm) < 0.0;
+        if !reflected && !refracted {
+            return false;
+        }
+
+        // the microfacet density and the change of variables cancel out.
+        // NB: like the smooth case, refraction doesn't rescale radiance by eta²
+        *attenuation = self.transmittance(r_in, rec) * fresnel_weight
+            * (self.distribution.g(wo, wi) / self.distribution.g1(wo));
+        *scattered = carry(&single, Ray::timed(rec.p, frame.transform(wi), r_in.time()));
+        true
+    }
+
+    /// Shading frame directions for a rough surface, with the relative eta
+    /// and the microfacet normal that would send [r_in] along [direction]
+    fn rough_configuration(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Option<RoughConfiguration> {
+        if self.distribution.effectively_smooth() {
+            return None;
+        }
+
+        let frame = shading_frame(rec);
+        let wo = frame.to_local(-unit_vector(*r_in.direction()));
+        let wi = frame.to_local(unit_vector(direction));
+        if wo.z() <= 0.0 || wi.z() == 0.0 {
+            return None;
+        }
+
+        // with dispersion, every wavelength refracts its own way, which only
+        // scattering follows
+        if wi.z() < 0.0 && self.dispersion.is_some() {
+            return None;
+        }
+
+        // generalized half vector
+        let etap = self.relative_eta(rec);
+        let half = if wi.z() > 0.0 { wo + wi } else { etap * wi + wo };
+        if half.near_zero() {
+            return None;
+        }
+        let mut wm = unit_vector(half);
+        if wm.z() < 0.0 {
+            wm = -wm;
+        }
+
+        // microfacets facing away from either direction contribute nothing
+        if dot(wm, wi) * wi.z() < 0.0 || dot(wm, wo) < 0.0 {
+            return None;
+        }
+
+        Some(RoughConfiguration { wo, wi, wm, etap })
+    }
+}
+
+struct RoughConfiguration {
+    wo: Vec3,
+    wi: Vec3,
+    wm: Vec3,
+    etap: f64,
 }
 
 impl Material for Dielectric {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
-        *attenuation = Color::new(1.0, 1.0, 1.0);
+        if !self.distribution.effectively_smooth() {
+            return self.scatter_rough(r_in, rec, attenuation, scattered);
+        }
+
+        *attenuation = self.transmittance(r_in, rec);
 
         let ri = if rec.front_face {
-            (1.0 / self.refraction_index)
+            1.0 / self.refraction_index
         } else { self.refraction_index };
 
         let unit_direction = unit_vector(*r_in.direction());
@@ -97,15 +620,626 @@ impl Material for Dielectric {
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = ri * sin_theta > 1.0;
-        let direction: Vec3;
+        let mut single = None;
+        let direction = if (!cannot_refract && self.film.is_some()) || self.dispersion.is_some() {
+            // reflectance varies by wavelength: pick by its average, and let
+            // the weight carry its color
+            let reflectance = self.fresnel(r_in, rec, cos_theta);
+            let reflect_probability = average(reflectance);
+            if random_f64() < reflect_probability {
+                *attenuation = *attenuation * reflectance / reflect_probability;
+                reflect(unit_direction, rec.normal)
+            } else {
+                let (etap, refracted) = self.disperse(r_in, rec);
+                if sin_theta > etap {
+                    // the wavelength followed reflects totally, and has zero weight
+                    return false;
+                }
 
-        if cannot_refract || Dielectric::reflectance(cos_theta, ri) > random_f64() {
-            direction = reflect(unit_direction, rec.normal);
+                if let Some(refracted) = &refracted {
+                    *attenuation = *attenuation * refracted.weight;
+                }
+                single = refracted;
+                *attenuation = *attenuation * (Color::new(1.0, 1.0, 1.0) - reflectance) / (1.0 - reflect_probability);
+                refract(unit_direction, rec.normal, 1.0 / etap)
+            }
+        } else if cannot_refract || Dielectric::reflectance(cos_theta, ri) > random_f64() {
+            reflect(unit_direction, rec.normal)
         } else {
-            direction = refract(unit_direction, rec.normal, ri);
+            refract(unit_direction, rec.normal, ri)
+        };
+
+        *scattered = carry(&single, Ray::timed(rec.p, direction, r_in.time()));
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let RoughConfiguration { wo, wi, wm, etap } = match self.rough_configuration(r_in, rec, direction) {
+            Some(configuration) => configuration,
+            None => return Color::zero(),
+        };
+
+        let d = self.distribution.d(wm);
+        let g = self.distribution.g(wo, wi);
+        let reflectance = self.fresnel(r_in, rec, dot(wo, wm));
+
+        let value = if wi.z() > 0.0 {
+            (d * g / (4.0 * wo.z())) * reflectance
+        } else {
+            let denom = dot(wi, wm) + dot(wo, wm) / etap;
+            let transmitted = Color::new(1.0, 1.0, 1.0) - reflectance;
+            (d * g * (dot(wi, wm) * dot(wo, wm)).abs() / (wo.z() * denom * denom)) * transmitted
+        };
+
+        value * self.transmittance(r_in, rec)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        let RoughConfiguration { wo, wi, wm, etap } = match self.rough_configuration(r_in, rec, direction) {
+            Some(configuration) => configuration,
+            None => return 0.0,
+        };
+
+        let pdf_wm = self.distribution.pdf(wo, wm);
+        let reflectance = average(self.fresnel(r_in, rec, dot(wo, wm)));
+
+        // change of variables from microfacet normal to scattered direction
+        if wi.z() > 0.0 {
+            pdf_wm / (4.0 * dot(wo, wm).abs()) * reflectance
+        } else {
+            let denom = dot(wi, wm) + dot(wo, wm) / etap;
+            pdf_wm * dot(wi, wm).abs() / (denom * denom) * (1.0 - reflectance)
         }
+    }
+}
+
+/// Clear dielectric coating, like varnish or car paint lacquer, layered
+/// over any [base] material. Light either reflects off the coating or
+/// refracts through it, bounces between the base and the underside of
+/// the coating, and refracts back out, absorbed on the way in and out
+pub struct Coated {
+    base: Arc<dyn Material>,
+    refraction_index: f64,
+    /// microfacet roughness of the coating surface, smooth unless set
+    distribution: TrowbridgeReitz,
+    /// absorption coefficient times thickness of the coating, per color channel
+    optical_depth: Color,
+}
+
+impl Coated {
+    pub fn new(base: Arc<dyn Material>, refraction_index: f64) -> Self {
+        Self {
+            base,
+            refraction_index,
+            distribution: TrowbridgeReitz::new(0.0, 0.0),
+            optical_depth: Color::zero(),
+        }
+    }
+
+    pub fn with_roughness(mut self, roughness: f64) -> Self {
+        self.distribution = TrowbridgeReitz::from_roughness(roughness, roughness);
+        self
+    }
+
+    /// Tinted coating of the given [thickness], absorbing light with
+    /// coefficient [absorption] (Beer-Lambert)
+    pub fn with_absorption(mut self, absorption: Color, thickness: f64) -> Self {
+        self.optical_depth = thickness * absorption;
+        self
+    }
+
+    /// Whether the hit at [rec] along [r_in] reflects off the coating,
+    /// with probability [reflectance], rather than going through it.
+    /// Picked by a hash of the hit like [MixMaterial::choose], so that
+    /// scattering, evaluating and densities all agree on it; the index of
+    /// refraction keeps the choice apart from a mix's at the same hit
+    fn reflects(&self, r_in: &Ray, rec: &HitRecord, reflectance: f64) -> bool {
+        let direction = r_in.direction();
+        let values = [rec.p.x(), rec.p.y(), rec.p.z(), direction.x(), direction.y(), direction.z(), self.refraction_index];
+
+        hashed_random(values) < reflectance
+    }
+
+    /// Fraction of light along [r_in] left after crossing the coating down
+    /// and back up, at the given cosines inside it
+    fn transmittance(&self, r_in: &Ray, cos_in: f64, cos_out: f64) -> Color {
+        let path = 1.0 / cos_in + 1.0 / cos_out;
+        evaluate_along(r_in, |lambda| (-rgb_at(self.optical_depth, lambda) * path).exp())
+    }
 
-        *scattered = Ray::timed(rec.p, direction, r_in.time());
+    /// Direction inside the coating, pointing away from the base, that
+    /// refracts into or out of [w] above it
+    fn inside_direction(&self, w: Vec3) -> Vec3 {
+        -refract(-w, Vec3::new(0.0, 0.0, 1.0), 1.0 / self.refraction_index)
+    }
+
+    /// Ray reaching the base from local direction [down] inside the coating,
+    /// on the wavelengths of [r_in]
+    fn base_ray(&self, r_in: &Ray, frame: &Onb, down: Vec3) -> Ray {
+        Ray::timed(*r_in.origin(), frame.transform(down), r_in.time())
+            .with_wavelengths(r_in.wavelengths())
+            .with_channel(r_in.channel())
+    }
+
+    /// Scale from a value per solid angle inside the coating to one per
+    /// solid angle outside it, for light refracting out into [wi], times
+    /// the share that gets out
+    fn exit_scale(&self, wi: Vec3, wi_inside: Vec3) -> f64 {
+        let eta2 = self.refraction_index * self.refraction_index;
+        (1.0 - fr_dielectric(wi.z(), self.refraction_index)) * wi.z() / (wi_inside.z() * eta2)
+    }
+}
+
+impl Material for Coated {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
+        let frame = shading_frame(rec);
+        let wo = frame.to_local(-unit_vector(*r_in.direction()));
+        if wo.z() <= 0.0 {
+            return false;
+        }
+
+        let reflectance = fr_dielectric(wo.z(), self.refraction_index);
+        if self.reflects(r_in, rec, reflectance) {
+            if self.distribution.effectively_smooth() {
+                *attenuation = Color::new(1.0, 1.0, 1.0);
+                *scattered = Ray::timed(rec.p, frame.transform(Vec3::new(-wo.x(), -wo.y(), wo.z())), r_in.time());
+                return true;
+            }
+
+            let wm = self.distribution.sample_wm(wo);
+            let wi = reflect(-wo, wm);
+            if wi.z() <= 0.0 {
+                return false;
+            }
+
+            // chosen by the reflectance at the macrosurface, weighed by the microfacet's
+            let weight = fr_dielectric(dot(wo, wm), self.refraction_index) / reflectance
+                * self.distribution.g(wo, wi) / self.distribution.g1(wo);
+            *attenuation = Color::new(weight, weight, weight);
+            *scattered = Ray::timed(rec.p, frame.transform(wi), r_in.time());
+            return true;
+        }
+
+        // through the coating, which is treated as smooth from the inside,
+        // bouncing between the base and the underside of the coating until
+        // the light gets out
+        let mut down = -self.inside_direction(wo);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        for _ in 0..MAX_COATING_BOUNCES {
+            let mut base_attenuation = Color::zero();
+            let mut base_scattered = Ray::default();
+            if !self.base.scatter(&self.base_ray(r_in, &frame, down), rec, &mut base_attenuation, &mut base_scattered) {
+                return false;
+            }
+
+            let up = frame.to_local(unit_vector(*base_scattered.direction()));
+            if up.z() <= 0.0 {
+                return false;
+            }
+            throughput = throughput * base_attenuation * self.transmittance(r_in, -down.z(), up.z());
+
+            // reflected back down in proportion to the reflectance from
+            // inside, which is all of it past the critical angle
+            if random_f64() < fr_dielectric(-up.z(), self.refraction_index) {
+                down = Vec3::new(up.x(), up.y(), -up.z());
+                continue;
+            }
+
+            let wi = refract(up, Vec3::new(0.0, 0.0, -1.0), self.refraction_index);
+            *attenuation = throughput;
+            *scattered = Ray::timed(rec.p, frame.transform(wi), r_in.time());
+            return true;
+        }
+
+        false
+    }
+
+    /// Reflection off a rough coating, or the light the base sends out
+    /// through the coating, whichever [scatter] picks for this hit. The
+    /// bounces under the coating are followed the way [scatter] does,
+    /// gathering light toward [direction] off the base at each of them
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let frame = shading_frame(rec);
+        let wo = frame.to_local(-unit_vector(*r_in.direction()));
+        let wi = frame.to_local(unit_vector(direction));
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Color::zero();
+        }
+
+        let reflectance = fr_dielectric(wo.z(), self.refraction_index);
+        if self.reflects(r_in, rec, reflectance) {
+            if self.distribution.effectively_smooth() {
+                return Color::zero();
+            }
+
+            let wm = unit_vector(wo + wi);
+            let d = self.distribution.d(wm);
+            let g = self.distribution.g(wo, wi);
+            let value = d * g * fr_dielectric(dot(wo, wm), self.refraction_index) / (4.0 * wo.z() * reflectance);
+            return Color::new(value, value, value);
+        }
+
+        let wi_inside = self.inside_direction(wi);
+        let toward = frame.transform(wi_inside);
+        let mut down = -self.inside_direction(wo);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut value = Color::zero();
+        for _ in 0..MAX_COATING_BOUNCES {
+            let base_in = self.base_ray(r_in, &frame, down);
+            value += throughput * self.base.eval(&base_in, rec, toward) * self.transmittance(r_in, -down.z(), wi_inside.z());
+
+            // on to the next bounce, if the light takes one
+            let mut base_attenuation = Color::zero();
+            let mut base_scattered = Ray::default();
+            if !self.base.scatter(&base_in, rec, &mut base_attenuation, &mut base_scattered) {
+                break;
+            }
+            let up = frame.to_local(unit_vector(*base_scattered.direction()));
+            if up.z() <= 0.0 || random_f64() >= fr_dielectric(-up.z(), self.refraction_index) {
+                break;
+            }
+            throughput = throughput * base_attenuation * self.transmittance(r_in, -down.z(), up.z());
+            down = Vec3::new(up.x(), up.y(), -up.z());
+        }
+
+        self.exit_scale(wi, wi_inside) * value
+    }
+
+    /// Density of the coating's reflection, or of the light getting out
+    /// after one bounce off the base, which is how most of it leaves
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        let frame = shading_frame(rec);
+        let wo = frame.to_local(-unit_vector(*r_in.direction()));
+        let wi = frame.to_local(unit_vector(direction));
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return 0.0;
+        }
+
+        let reflectance = fr_dielectric(wo.z(), self.refraction_index);
+        if self.reflects(r_in, rec, reflectance) {
+            if self.distribution.effectively_smooth() {
+                return 0.0;
+            }
+
+            let wm = unit_vector(wo + wi);
+            return self.distribution.pdf(wo, wm) / (4.0 * dot(wo, wm).abs());
+        }
+
+        let wi_inside = self.inside_direction(wi);
+        let base_in = self.base_ray(r_in, &frame, -self.inside_direction(wo));
+
+        self.base.pdf(&base_in, rec, frame.transform(wi_inside)) * self.exit_scale(wi, wi_inside)
+    }
+
+    fn opacity(&self, u: f64, v: f64, p: &Point3) -> f64 {
+        self.base.opacity(u, v, p)
+    }
+}
+
+/// Translucent material like skin, wax, marble or milk: light refracts in
+/// through a dielectric boundary, takes a random walk of scattering events
+/// inside, and refracts back out somewhere else. Needs a closed surface,
+/// and a camera [max_depth] high enough for the walks
+pub struct Subsurface {
+    /// boundary between the inside and the outside
+    interface: Dielectric,
+    /// fraction of the light each scattering event inside keeps, per color channel
+    single_scattering_albedo: Color,
+    /// chance of a scattering event per unit distance, per color channel
+    extinction: Color,
+}
+
+impl Subsurface {
+    /// [albedo] is the color the surface ends up looking, and
+    /// [mean_free_path] the average distance light goes inside between
+    /// scattering events, per color channel
+    pub fn new(albedo: Color, mean_free_path: Color, refraction_index: f64) -> Self {
+        // inverts the albedo a random walk ends up with (Chiang et al. 2016)
+        let single_scattering = |a: f64| {
+            let a = a.clamp(0.0, 1.0);
+            let s = 4.09712 + 4.20863 * a - (9.59217 + 41.6808 * a + 17.7126 * a * a).sqrt();
+            1.0 - s * s
+        };
+
+        Self {
+            interface: Dielectric::new(refraction_index),
+            single_scattering_albedo: Color::new(
+                single_scattering(albedo.x()),
+                single_scattering(albedo.y()),
+                single_scattering(albedo.z()),
+            ),
+            extinction: Color::new(1.0 / mean_free_path.x(), 1.0 / mean_free_path.y(), 1.0 / mean_free_path.z()),
+        }
+    }
+
+    pub fn with_roughness(mut self, roughness: f64) -> Self {
+        self.interface = self.interface.with_roughness(roughness);
+        self
+    }
+}
+
+impl Material for Subsurface {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
+        if rec.front_face {
+            return self.interface.scatter(r_in, rec, attenuation, scattered);
+        }
+
+        // extinction that differs by color sends each wavelength its own way,
+        // so the walk then follows just one
+        let single = if self.extinction.x() == self.extinction.y() && self.extinction.y() == self.extinction.z() {
+            None
+        } else {
+            Some(SingleWavelength::along(r_in))
+        };
+        let (extinction, weight) = match &single {
+            Some(single) => (rgb_at(self.extinction, single.lambda), single.weight),
+            None => (self.extinction.x(), Color::new(1.0, 1.0, 1.0)),
+        };
+
+        // free flight from inside toward the boundary
+        let length = r_in.direction().length();
+        let flight = -(1.0 - random_f64()).ln() / extinction;
+
+        if flight < rec.t * length {
+            // scatters inside first, evenly in every direction
+            let albedo = evaluate_along(r_in, |lambda| rgb_at(self.single_scattering_albedo, lambda));
+            *attenuation = weight * albedo;
+            *scattered = carry(&single, Ray::timed(r_in.at(flight / length), random_unit_vector(), r_in.time()));
+            return true;
+        }
+
+        // reaches the boundary, to leave or reflect back in
+        let mut boundary = Color::zero();
+        let mut boundary_scattered = Ray::default();
+        if !self.interface.scatter(r_in, rec, &mut boundary, &mut boundary_scattered) {
+            return false;
+        }
+
+        *attenuation = weight * boundary;
+        *scattered = carry(&single, boundary_scattered);
         true
     }
-}
\ No newline at end of file
+
+    // eval and pdf stay zero: paths only find the light after the walk, by scattering
+}
+
+/// Wraps any material with an opacity mask, for leaves, fences and other
+/// shapes cut out of a surface. Rays go through where [opacity] is black
+pub struct Cutout {
+    inner: Arc<dyn Material>,
+    opacity: Arc<dyn Texture>,
+}
+
+impl Cutout {
+    pub fn new(inner: Arc<dyn Material>, opacity: Arc<dyn Texture>) -> Self {
+        Self { inner, opacity }
+    }
+}
+
+impl Material for Cutout {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
+        self.inner.scatter(r_in, rec, attenuation, scattered)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        self.inner.eval(r_in, rec, direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        self.inner.pdf(r_in, rec, direction)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        self.inner.emitted(r_in, rec)
+    }
+
+    fn opacity(&self, u: f64, v: f64, p: &Point3) -> f64 {
+        luminance(self.opacity.value(u, v, p)).clamp(0.0, 1.0) * self.inner.opacity(u, v, p)
+    }
+}
+
+/// What decides how much of the second material a [MixMaterial] shows
+enum MixWeight {
+    /// luminance of a texture
+    Texture(Arc<dyn Texture>),
+    /// reflectance of a dielectric with this index of refraction, for the
+    /// second material as a sheen that grows at grazing angles
+    Fresnel(f64),
+}
+
+/// Blend of two materials, like rust painted onto metal or dirt onto
+/// paint. Each hit shows one or the other at random, in proportion to the
+/// weight, and sticks to that choice for all its light
+pub struct MixMaterial {
+    first: Arc<dyn Material>,
+    second: Arc<dyn Material>,
+    weight: MixWeight,
+}
+
+impl MixMaterial {
+    /// [second] shows where [weight] is white, [first] where it's black
+    pub fn new(first: Arc<dyn Material>, second: Arc<dyn Material>, weight: Arc<dyn Texture>) -> Self {
+        Self { first, second, weight: MixWeight::Texture(weight) }
+    }
+
+    /// [second] shows as much as a dielectric of [refraction_index] reflects
+    pub fn fresnel(first: Arc<dyn Material>, second: Arc<dyn Material>, refraction_index: f64) -> Self {
+        Self { first, second, weight: MixWeight::Fresnel(refraction_index) }
+    }
+
+    /// The material the hit at [rec] along [r_in] shows. Picked by a hash of
+    /// the hit rather than at random, so that scattering, evaluating and
+    /// densities all agree on it
+    fn choose(&self, r_in: &Ray, rec: &HitRecord) -> &dyn Material {
+        let weight = match &self.weight {
+            MixWeight::Texture(texture) => luminance(texture.value(rec.u, rec.v, &rec.p)),
+            MixWeight::Fresnel(refraction_index) => {
+                let cos_theta = dot(-unit_vector(*r_in.direction()), rec.normal);
+                fr_dielectric(cos_theta, *refraction_index)
+            }
+        };
+
+        let direction = r_in.direction();
+        let u = hashed_random([rec.p.x(), rec.p.y(), rec.p.z(), direction.x(), direction.y(), direction.z()]);
+
+        if u < weight { self.second.as_ref() } else { self.first.as_ref() }
+    }
+}
+
+impl Material for MixMaterial {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
+        self.choose(r_in, rec).scatter(r_in, rec, attenuation, scattered)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        self.choose(r_in, rec).eval(r_in, rec, direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        self.choose(r_in, rec).pdf(r_in, rec, direction)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        self.choose(r_in, rec).emitted(r_in, rec)
+    }
+
+    fn opacity(&self, u: f64, v: f64, p: &Point3) -> f64 {
+        match &self.weight {
+            MixWeight::Texture(texture) => {
+                let weight = luminance(texture.value(u, v, p)).clamp(0.0, 1.0);
+                (1.0 - weight) * self.first.opacity(u, v, p) + weight * self.second.opacity(u, v, p)
+            }
+            // a sheen covers the first material, so it decides
+            MixWeight::Fresnel(_) => self.first.opacity(u, v, p),
+        }
+    }
+}
+
+/// Different materials on the two faces of a surface, like paper printed
+/// differently on each side. The front face is the one the surface
+/// normal points out of
+pub struct TwoSided {
+    front: Arc<dyn Material>,
+    back: Arc<dyn Material>,
+}
+
+impl TwoSided {
+    pub fn new(front: Arc<dyn Material>, back: Arc<dyn Material>) -> Self {
+        Self { front, back }
+    }
+
+    fn side(&self, rec: &HitRecord) -> &dyn Material {
+        if rec.front_face { self.front.as_ref() } else { self.back.as_ref() }
+    }
+}
+
+impl Material for TwoSided {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
+        self.side(rec).scatter(r_in, rec, attenuation, scattered)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        self.side(rec).eval(r_in, rec, direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        self.side(rec).pdf(r_in, rec, direction)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        self.side(rec).emitted(r_in, rec)
+    }
+
+    // hits are cut out before their face is known, so the front decides
+    fn opacity(&self, u: f64, v: f64, p: &Point3) -> f64 {
+        self.front.opacity(u, v, p)
+    }
+}
+
+/// Diffuse Light (emits evenly in every direction, scatters nothing)
+pub struct DiffuseLight {
+    emit: Color,
+    /// dark from behind, like a panel light
+    one_sided: bool,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self { emit, one_sided: false }
+    }
+
+    /// Emits from the front face only, the side the surface normal points to
+    pub fn one_sided(emit: Color) -> Self {
+        Self { emit, one_sided: true }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _attenuation: &mut Color, _scattered: &mut Ray) -> bool {
+        false
+    }
+
+    fn emitted(&self, _r_in: &Ray, rec: &HitRecord) -> Color {
+        if self.one_sided && !rec.front_face {
+            return Color::zero();
+        }
+
+        self.emit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3::random_on_hemisphere;
+
+    /// Hit of [mat] facing +y, at a random point near the origin so that
+    /// choices made by hashing the hit vary
+    fn hit(mat: Arc<dyn Material>) -> HitRecord {
+        HitRecord {
+            p: Point3::new(random_f64(), 0.0, random_f64()),
+            normal: Vec3::new(0.0, 1.0, 0.0),
+            dpdu: Vec3::new(1.0, 0.0, 0.0),
+            front_face: true,
+            mat,
+            ..HitRecord::default()
+        }
+    }
+
+    /// Fraction of the light arriving from [incoming] that [mat] reflects,
+    /// estimated by scattering and by integrating [Material::eval]
+    fn albedos(mat: Arc<dyn Material>, incoming: Vec3) -> (f64, f64) {
+        let samples = 50_000;
+        let r_in = Ray::new(Point3::zero(), -incoming);
+
+        let mut scattered_total = 0.0;
+        let mut evaluated_total = 0.0;
+        for _ in 0..samples {
+            let rec = hit(mat.clone());
+            let mut attenuation = Color::zero();
+            let mut scattered = Ray::default();
+            if mat.scatter(&r_in, &rec, &mut attenuation, &mut scattered) {
+                scattered_total += attenuation.y();
+            }
+
+            let rec = hit(mat.clone());
+            evaluated_total += 2.0 * PI * mat.eval(&r_in, &rec, random_on_hemisphere(rec.normal)).y();
+        }
+
+        (scattered_total / samples as f64, evaluated_total / samples as f64)
+    }
+
+    #[test]
+    fn coated_eval_agrees_with_scatter() {
+        let incoming = unit_vector(Vec3::new(1.0, 1.0, 0.0));
+
+        for base in [
+            Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8))) as Arc<dyn Material>,
+            Arc::new(Metal::new(Color::new(0.9, 0.9, 0.9), 0.7)),
+        ] {
+            let coated = Arc::new(Coated::new(base, 1.5).with_roughness(0.7));
+            let (scattered, evaluated) = albedos(coated, incoming);
+            assert!((scattered - evaluated).abs() < 0.02, "{} scattered, {} evaluated", scattered, evaluated);
+        }
+    }
+}
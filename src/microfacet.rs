@@ -0,0 +1,121 @@
+use std::f64::consts::PI;
+use crate::rt_weekend::random_f64;
+use crate::vec3::{cross, dot, unit_vector, Vec3};
+
+/// Trowbridge-Reitz (GGX) distribution of microfacet normals, with Smith
+/// masking-shadowing. Works in a local shading frame where the
+/// macrosurface normal is +z
+pub struct TrowbridgeReitz {
+    alpha_x: f64,
+    alpha_y: f64,
+}
+
+impl TrowbridgeReitz {
+    pub fn new(alpha_x: f64, alpha_y: f64) -> Self {
+        Self { alpha_x, alpha_y }
+    }
+
+    /// Perceptual roughness in [0,1] along each tangent axis.
+    /// Squaring it makes roughness changes look about as large everywhere
+    pub fn from_roughness(roughness_x: f64, roughness_y: f64) -> Self {
+        let alpha = |roughness: f64| roughness.clamp(0.0, 1.0).powi(2);
+        Self::new(alpha(roughness_x), alpha(roughness_y))
+    }
+
+    /// Surfaces this smooth are treated as perfect specular reflectors
+    pub fn effectively_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < 1e-3
+    }
+
+    /// Density of microfacets with normal [wm], per unit projected area
+    pub fn d(&self, wm: Vec3) -> f64 {
+        let tan2_theta = tan2_theta(wm);
+        if tan2_theta.is_infinite() {
+            return 0.0;
+        }
+
+        let cos4_theta = wm.z().powi(4);
+        let (cos_phi, sin_phi) = cos_sin_phi(wm);
+        let e = tan2_theta * ((cos_phi / self.alpha_x).powi(2) + (sin_phi / self.alpha_y).powi(2));
+
+        1.0 / (PI * self.alpha_x * self.alpha_y * cos4_theta * (1.0 + e).powi(2))
+    }
+
+    fn lambda(&self, w: Vec3) -> f64 {
+        let tan2_theta = tan2_theta(w);
+        if tan2_theta.is_infinite() {
+            return 0.0;
+        }
+
+        let (cos_phi, sin_phi) = cos_sin_phi(w);
+        let alpha2 = (cos_phi * self.alpha_x).powi(2) + (sin_phi * self.alpha_y).powi(2);
+
+        ((1.0 + alpha2 * tan2_theta).sqrt() - 1.0) / 2.0
+    }
+
+    /// Fraction of microfacets visible from [w]
+    pub fn g1(&self, w: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Fraction of microfacets visible from both [wo] and [wi]
+    pub fn g(&self, wo: Vec3, wi: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Density of microfacet normals [wm] visible from [w]
+    pub fn pdf(&self, w: Vec3, wm: Vec3) -> f64 {
+        let cos_theta = w.z().abs();
+        if cos_theta == 0.0 {
+            return 0.0;
+        }
+
+        self.g1(w) / cos_theta * self.d(wm) * dot(w, wm).abs()
+    }
+
+    /// Samples a microfacet normal visible from [w], after Heitz,
+    /// "Sampling the GGX Distribution of Visible Normals" (2018)
+    pub fn sample_wm(&self, w: Vec3) -> Vec3 {
+        // stretch the view direction into the hemisphere configuration
+        let mut wh = unit_vector(Vec3::new(self.alpha_x * w.x(), self.alpha_y * w.y(), w.z()));
+        if wh.z() < 0.0 {
+            wh = -wh;
+        }
+
+        let t1 = if wh.z() < 0.99999 {
+            unit_vector(cross(Vec3::new(0.0, 0.0, 1.0), wh))
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = cross(wh, t1);
+
+        // uniform point on the disk, warped to the visible projected hemisphere
+        let r = random_f64().sqrt();
+        let phi = 2.0 * PI * random_f64();
+        let px = r * phi.cos();
+        let mut py = r * phi.sin();
+        let h = (1.0 - px * px).sqrt();
+        let s = (1.0 + wh.z()) / 2.0;
+        py = (1.0 - s) * h + s * py;
+        let pz = (1.0 - px * px - py * py).max(0.0).sqrt();
+
+        let nh = px * t1 + py * t2 + pz * wh;
+
+        // back to the ellipsoid configuration
+        unit_vector(Vec3::new(self.alpha_x * nh.x(), self.alpha_y * nh.y(), nh.z().max(1e-6)))
+    }
+}
+
+fn tan2_theta(w: Vec3) -> f64 {
+    let cos2_theta = w.z() * w.z();
+    (1.0 - cos2_theta).max(0.0) / cos2_theta
+}
+
+fn cos_sin_phi(w: Vec3) -> (f64, f64) {
+    let sin_theta = (w.x() * w.x() + w.y() * w.y()).sqrt();
+    if sin_theta == 0.0 {
+        return (1.0, 0.0);
+    }
+
+    ((w.x() / sin_theta).clamp(-1.0, 1.0), (w.y() / sin_theta).clamp(-1.0, 1.0))
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+use crate::color::{luminance, Color};
+use crate::hittable::HitRecord;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::vec3::{cross, dot, unit_vector, Point3, Vec3};
+
+/// Step in surface coordinates for the slopes of a height map
+const BUMP_DELTA: f64 = 0.0005;
+
+/// Where the shading normal comes from
+enum Perturbation {
+    /// tangent-space normals stored as 0.5 + 0.5n, with x along dpdu,
+    /// y along dpdv and z out of the surface
+    NormalMap(Arc<dyn Texture>),
+    /// heights above the surface: the luminance of the texture times [scale]
+    BumpMap { heights: Arc<dyn Texture>, scale: f64 },
+}
+
+/// Wraps any material to shade it with a normal perturbed by a texture,
+/// for surface detail the geometry doesn't have
+pub struct NormalMapped {
+    inner: Arc<dyn Material>,
+    perturbation: Perturbation,
+}
+
+impl NormalMapped {
+    /// [normals] is a tangent-space normal map, as image textures loaded
+    /// with `ImageTexture::load_data`
+    pub fn new(inner: Arc<dyn Material>, normals: Arc<dyn Texture>) -> Self {
+        Self { inner, perturbation: Perturbation::NormalMap(normals) }
+    }
+
+    /// Bumps from a height map, [scale] world units high where [heights] is white
+    pub fn bumped(inner: Arc<dyn Material>, heights: Arc<dyn Texture>, scale: f64) -> Self {
+        Self { inner, perturbation: Perturbation::BumpMap { heights, scale } }
+    }
+
+    /// Perturbed normal at [rec], pointing out of the surface
+    fn outward_normal(&self, rec: &HitRecord) -> Vec3 {
+        let outward = if rec.front_face { rec.normal } else { -rec.normal };
+
+        match &self.perturbation {
+            Perturbation::NormalMap(normals) => {
+                let local = 2.0 * normals.value(rec.u, rec.v, &rec.p) - Color::new(1.0, 1.0, 1.0);
+                let frame = Onb::with_tangent(outward, rec.dpdu);
+                // the bitangent follows v, whichever way the tangents wind
+                let bitangent = if dot(frame.v(), rec.dpdv) < 0.0 { -frame.v() } else { frame.v() };
+
+                let n = local.x() * frame.u() + local.y() * bitangent + local.z() * frame.w();
+                if n.near_zero() { outward } else { unit_vector(n) }
+            }
+            Perturbation::BumpMap { heights, scale } => {
+                let height = |du: f64, dv: f64| {
+                    let p = rec.p + du * rec.dpdu + dv * rec.dpdv;
+                    scale * luminance(heights.value(rec.u + du, rec.v + dv, &p))
+                };
+
+                let h = height(0.0, 0.0);
+                let dhdu = (height(BUMP_DELTA, 0.0) - h) / BUMP_DELTA;
+                let dhdv = (height(0.0, BUMP_DELTA) - h) / BUMP_DELTA;
+
+                // tangents of the displaced surface
+                let n = cross(rec.dpdu + dhdu * outward, rec.dpdv + dhdv * outward);
+                if n.near_zero() {
+                    return outward;
+                }
+
+                let n = unit_vector(n);
+                if dot(n, outward) < 0.0 { -n } else { n }
+            }
+        }
+    }
+
+    /// [rec] as the inner material sees it, with the shading normal in
+    /// place of the geometric one
+    fn shading_record(&self, r_in: &Ray, rec: &HitRecord) -> HitRecord {
+        let outward = self.outward_normal(rec);
+        let n = if rec.front_face { outward } else { -outward };
+        let wo = -unit_vector(*r_in.direction());
+
+        let mut shading = rec.clone();
+        shading.normal = ensure_valid_reflection(rec.normal, wo, n);
+        shading
+    }
+}
+
+/// Bends the shading normal [n] toward the geometric normal [ng] as much as
+/// needed for the mirror reflection of [wo] to stay above the surface, so
+/// that [wo] never sees the back of the shading normal
+fn ensure_valid_reflection(ng: Vec3, wo: Vec3, n: Vec3) -> Vec3 {
+    let r = 2.0 * dot(wo, n) * n - wo;
+    let threshold = (0.9 * dot(wo, ng)).min(0.01);
+    if dot(r, ng) >= threshold {
+        return n;
+    }
+
+    let r = unit_vector(r + (threshold - dot(r, ng)) * ng);
+    unit_vector(wo + r)
+}
+
+/// Whether [direction] is on the same side of the geometric and the shading
+/// normal. Directions in between would let light through the surface
+fn same_side(rec: &HitRecord, shading: &HitRecord, direction: Vec3) -> bool {
+    dot(direction, rec.normal) * dot(direction, shading.normal) > 0.0
+}
+
+impl Material for NormalMapped {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
+        let shading = self.shading_record(r_in, rec);
+        if !self.inner.scatter(r_in, &shading, attenuation, scattered) {
+            return false;
+        }
+
+        // volume scattering under the surface, like subsurface walks, starts elsewhere
+        let from_surface = (*scattered.origin() - rec.p).near_zero();
+        !from_surface || same_side(rec, &shading, *scattered.direction())
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let shading = self.shading_record(r_in, rec);
+        if !same_side(rec, &shading, direction) {
+            return Color::zero();
+        }
+
+        self.inner.eval(r_in, &shading, direction)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        let shading = self.shading_record(r_in, rec);
+        if !same_side(rec, &shading, direction) {
+            return 0.0;
+        }
+
+        self.inner.pdf(r_in, &shading, direction)
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        self.inner.emitted(r_in, rec)
+    }
+
+    fn opacity(&self, u: f64, v: f64, p: &Point3) -> f64 {
+        self.inner.opacity(u, v, p)
+    }
+}
@@ -0,0 +1,50 @@
+use crate::vec3::{cross, dot, unit_vector, Vec3};
+
+/// Orthonormal basis around a given axis, used to build directions
+/// relative to a normal or a light's axis
+pub struct Onb {
+    axis: [Vec3; 3],
+}
+
+impl Onb {
+    /// [n] becomes the w (local z) axis
+    pub fn new(n: Vec3) -> Self {
+        let w = unit_vector(n);
+        let a = if w.x().abs() > 0.9 { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) };
+        let v = unit_vector(cross(w, a));
+        let u = cross(w, v);
+
+        Self { axis: [u, v, w] }
+    }
+
+    /// [n] becomes the w axis, and [tangent] the u axis once made
+    /// perpendicular to it. Falls back to [new] for tangents along [n]
+    pub fn with_tangent(n: Vec3, tangent: Vec3) -> Self {
+        let w = unit_vector(n);
+        let along = tangent - dot(tangent, w) * w;
+        if along.near_zero() {
+            return Self::new(n);
+        }
+
+        let u = unit_vector(along);
+        let v = cross(w, u);
+
+        Self { axis: [u, v, w] }
+    }
+
+    pub fn u(&self) -> Vec3 { self.axis[0] }
+
+    pub fn v(&self) -> Vec3 { self.axis[1] }
+
+    pub fn w(&self) -> Vec3 { self.axis[2] }
+
+    /// Local coordinates to world space
+    pub fn transform(&self, v: Vec3) -> Vec3 {
+        (v[0] * self.axis[0]) + (v[1] * self.axis[1]) + (v[2] * self.axis[2])
+    }
+
+    /// World space to local coordinates
+    pub fn to_local(&self, v: Vec3) -> Vec3 {
+        Vec3::new(dot(v, self.axis[0]), dot(v, self.axis[1]), dot(v, self.axis[2]))
+    }
+}
@@ -0,0 +1,336 @@
+use std::f64::consts::PI;
+use crate::color::{luminance, Color};
+use crate::fresnel::{fr_dielectric, fr_schlick};
+use crate::hittable::HitRecord;
+use crate::material::{shading_frame, Material};
+use crate::microfacet::TrowbridgeReitz;
+use crate::ray::Ray;
+use crate::rt_weekend::random_f64;
+use crate::spectrum::along_ray;
+use crate::vec3::{dot, random_unit_vector, reflect, refract, unit_vector, Vec3};
+
+/// Keeps the specular lobes glossy rather than perfect mirrors,
+/// so every direction they scatter in has a density
+const MIN_ALPHA: f64 = 1e-3;
+
+/// Disney's principled BSDF (Burley 2012, 2015): a diffuse base with
+/// sheen, specular reflection, a clearcoat and rough transmission, mixed
+/// by parameters in [0,1] that artists know from other tools
+pub struct Principled {
+    pub base_color: Color,
+    /// dielectric (0) to metal (1)
+    pub metallic: f64,
+    pub roughness: f64,
+    /// stretches highlights along the surface tangent; 0 is isotropic
+    pub anisotropic: f64,
+    /// dielectric reflectance at normal incidence, where 0.5 is 4%
+    pub specular: f64,
+    /// tints dielectric highlights toward the base color
+    pub specular_tint: f64,
+    /// extra reflection at grazing angles, for cloth
+    pub sheen: f64,
+    pub sheen_tint: f64,
+    /// strength of a second, colorless specular layer on top
+    pub clearcoat: f64,
+    /// satin (0) to glossy (1) clearcoat
+    pub clearcoat_gloss: f64,
+    /// opaque (0) to glass (1), for the dielectric part
+    pub transmission: f64,
+    /// index of refraction used for transmission
+    pub ior: f64,
+}
+
+/// Probabilities of sampling each lobe. The glass lobe reflects or
+/// refracts off each microfacet as the Fresnel equations say
+struct LobeWeights {
+    diffuse: f64,
+    specular: f64,
+    clearcoat: f64,
+    glass: f64,
+}
+
+impl Principled {
+    pub fn new(base_color: Color) -> Self {
+        Self {
+            base_color,
+            metallic: 0.0,
+            roughness: 0.5,
+            anisotropic: 0.0,
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            ior: 1.5,
+        }
+    }
+
+    fn distribution(&self) -> TrowbridgeReitz {
+        let aspect = (1.0 - 0.9 * self.anisotropic.clamp(0.0, 1.0)).sqrt();
+        let alpha = self.roughness.clamp(0.0, 1.0).powi(2);
+        TrowbridgeReitz::new((alpha / aspect).max(MIN_ALPHA), (alpha * aspect).max(MIN_ALPHA))
+    }
+
+    fn clearcoat_alpha(&self) -> f64 {
+        let gloss = self.clearcoat_gloss.clamp(0.0, 1.0);
+        0.1 * (1.0 - gloss) + 0.001 * gloss
+    }
+
+    /// Base color hue and saturation, at unit luminance
+    fn tint(&self) -> Color {
+        let l = luminance(self.base_color);
+        if l > 0.0 { self.base_color / l } else { Color::new(1.0, 1.0, 1.0) }
+    }
+
+    /// Hits from inside a transmissive object only see the glass lobes
+    fn inside(&self, rec: &HitRecord) -> bool {
+        !rec.front_face && self.transmission > 0.0
+    }
+
+    /// Index of refraction of the side light goes into over the side it comes from
+    fn relative_eta(&self, rec: &HitRecord) -> f64 {
+        if rec.front_face { self.ior } else { 1.0 / self.ior }
+    }
+
+    fn lobe_weights(&self, rec: &HitRecord) -> LobeWeights {
+        if self.inside(rec) {
+            return LobeWeights { diffuse: 0.0, specular: 0.0, clearcoat: 0.0, glass: 1.0 };
+        }
+
+        let metallic = self.metallic.clamp(0.0, 1.0);
+        let transmission = self.transmission.clamp(0.0, 1.0);
+
+        let diffuse = (1.0 - metallic) * (1.0 - transmission);
+        let specular = metallic + diffuse;
+        let clearcoat = 0.25 * self.clearcoat.clamp(0.0, 1.0);
+        let glass = (1.0 - metallic) * transmission;
+
+        let total = diffuse + specular + clearcoat + glass;
+        LobeWeights {
+            diffuse: diffuse / total,
+            specular: specular / total,
+            clearcoat: clearcoat / total,
+            glass: glass / total,
+        }
+    }
+
+    /// Fresnel term of the specular lobe, blending the metallic, opaque
+    /// dielectric and transmissive parts
+    fn specular_fresnel(&self, rec: &HitRecord, cos_theta: f64) -> Color {
+        let reflectance = fr_dielectric(cos_theta, self.relative_eta(rec));
+        if self.inside(rec) {
+            return Color::new(reflectance, reflectance, reflectance);
+        }
+
+        let metallic = self.metallic.clamp(0.0, 1.0);
+        let transmission = self.transmission.clamp(0.0, 1.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+
+        let tint = (1.0 - self.specular_tint) * white + self.specular_tint * self.tint();
+        let f0 = 0.08 * self.specular * tint;
+
+        metallic * fr_schlick(cos_theta, self.base_color)
+            + (1.0 - metallic) * (1.0 - transmission) * fr_schlick(cos_theta, f0)
+            + (1.0 - metallic) * transmission * reflectance * white
+    }
+
+    /// Scattering function times the cosine term, in the shading frame
+    fn eval_local(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        let distribution = self.distribution();
+
+        if wi.z() < 0.0 {
+            let (wm, denom) = match self.refraction_half_vector(rec, wo, wi) {
+                Some(half_vector) => half_vector,
+                None => return Color::zero(),
+            };
+            let weight = if self.inside(rec) { 1.0 } else { (1.0 - self.metallic) * self.transmission };
+            let reflectance = fr_dielectric(dot(wo, wm), self.relative_eta(rec));
+
+            // half the base color on the way in, half on the way out
+            let tint = Color::new(self.base_color.x().sqrt(), self.base_color.y().sqrt(), self.base_color.z().sqrt());
+            let value = distribution.d(wm) * distribution.g(wo, wi) * (1.0 - reflectance)
+                * (dot(wi, wm) * dot(wo, wm)).abs() / (wo.z() * denom * denom);
+            return (weight * value) * tint;
+        }
+
+        let half = wo + wi;
+        if half.near_zero() {
+            return Color::zero();
+        }
+        let wm = unit_vector(half);
+        let cos_d = dot(wi, wm);
+
+        let specular = self.specular_fresnel(rec, cos_d)
+            * (distribution.d(wm) * distribution.g(wo, wi) / (4.0 * wo.z()));
+        if self.inside(rec) {
+            return specular;
+        }
+
+        // Burley's retro-reflective diffuse, and sheen toward grazing angles
+        let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+        let fresnel_diffuse = |cos_theta: f64| 1.0 + (fd90 - 1.0) * (1.0 - cos_theta).powi(5);
+        let diffuse = self.base_color * (fresnel_diffuse(wi.z()) * fresnel_diffuse(wo.z()) / PI);
+
+        let sheen_color = (1.0 - self.sheen_tint) * Color::new(1.0, 1.0, 1.0) + self.sheen_tint * self.tint();
+        let sheen = self.sheen * (1.0 - cos_d).powi(5) * sheen_color;
+
+        let opaque = (1.0 - self.metallic) * (1.0 - self.transmission);
+        let mut value = specular + (opaque * wi.z()) * (diffuse + sheen);
+
+        if self.clearcoat > 0.0 {
+            let coat = gtr1(wm.z(), self.clearcoat_alpha()) * TrowbridgeReitz::new(0.25, 0.25).g(wo, wi)
+                / (4.0 * wo.z());
+            value += (0.25 * self.clearcoat * coat) * fr_schlick(cos_d, Color::new(0.04, 0.04, 0.04));
+        }
+
+        value
+    }
+
+    fn pdf_local(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        let weights = self.lobe_weights(rec);
+        let distribution = self.distribution();
+        let eta = self.relative_eta(rec);
+
+        if wi.z() < 0.0 {
+            let (wm, denom) = match self.refraction_half_vector(rec, wo, wi) {
+                Some(half_vector) => half_vector,
+                None => return 0.0,
+            };
+            let refracted = 1.0 - fr_dielectric(dot(wo, wm), eta);
+            return weights.glass * refracted * distribution.pdf(wo, wm) * dot(wi, wm).abs() / (denom * denom);
+        }
+
+        let half = wo + wi;
+        if half.near_zero() {
+            return 0.0;
+        }
+        let wm = unit_vector(half);
+
+        // change of variables from microfacet normal to reflected direction
+        let jacobian = 1.0 / (4.0 * dot(wo, wm));
+
+        let reflected = fr_dielectric(dot(wo, wm), eta);
+
+        weights.diffuse * wi.z() / PI
+            + (weights.specular + weights.glass * reflected) * distribution.pdf(wo, wm) * jacobian
+            + weights.clearcoat * gtr1(wm.z(), self.clearcoat_alpha()) * wm.z() * jacobian
+    }
+
+    fn sample_local(&self, rec: &HitRecord, wo: Vec3) -> Option<Vec3> {
+        let weights = self.lobe_weights(rec);
+        let u = random_f64();
+
+        let wi = if u < weights.diffuse {
+            let direction = Vec3::new(0.0, 0.0, 1.0) + random_unit_vector();
+            if direction.near_zero() {
+                return None;
+            }
+            unit_vector(direction)
+        } else if u < weights.diffuse + weights.specular {
+            reflect(-wo, self.distribution().sample_wm(wo))
+        } else if u < weights.diffuse + weights.specular + weights.clearcoat {
+            reflect(-wo, sample_gtr1(self.clearcoat_alpha()))
+        } else {
+            let eta = self.relative_eta(rec);
+            let wm = self.distribution().sample_wm(wo);
+            if random_f64() < fr_dielectric(dot(wo, wm), eta) {
+                reflect(-wo, wm)
+            } else {
+                refract(-wo, wm, 1.0 / eta)
+            }
+        };
+
+        Some(wi)
+    }
+
+    /// Microfacet normal that refracts [wo] into [wi], with the
+    /// denominator of the change of variables between the two
+    fn refraction_half_vector(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Option<(Vec3, f64)> {
+        if self.transmission <= 0.0 || self.metallic >= 1.0 {
+            return None;
+        }
+
+        let eta = self.relative_eta(rec);
+        let half = eta * wi + wo;
+        if half.near_zero() {
+            return None;
+        }
+        let mut wm = unit_vector(half);
+        if wm.z() < 0.0 {
+            wm = -wm;
+        }
+
+        // microfacets facing away from either direction contribute nothing
+        if dot(wm, wi) > 0.0 || dot(wm, wo) < 0.0 {
+            return None;
+        }
+
+        Some((wm, dot(wi, wm) + dot(wo, wm) / eta))
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, attenuation: &mut Color, scattered: &mut Ray) -> bool {
+        let frame = shading_frame(rec);
+        let wo = frame.to_local(-unit_vector(*r_in.direction()));
+        if wo.z() <= 0.0 {
+            return false;
+        }
+
+        let wi = match self.sample_local(rec, wo) {
+            Some(wi) if wi.z() != 0.0 => wi,
+            _ => return false,
+        };
+
+        // weigh by the density of all lobes together, not just the sampled one
+        let pdf = self.pdf_local(rec, wo, wi);
+        if pdf <= 0.0 {
+            return false;
+        }
+
+        *attenuation = along_ray(r_in, self.eval_local(rec, wo, wi) / pdf);
+        *scattered = Ray::timed(rec.p, frame.transform(wi), r_in.time());
+        true
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Color {
+        let frame = shading_frame(rec);
+        let wo = frame.to_local(-unit_vector(*r_in.direction()));
+        let wi = frame.to_local(unit_vector(direction));
+        if wo.z() <= 0.0 || wi.z() == 0.0 {
+            return Color::zero();
+        }
+
+        along_ray(r_in, self.eval_local(rec, wo, wi))
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        let frame = shading_frame(rec);
+        let wo = frame.to_local(-unit_vector(*r_in.direction()));
+        let wi = frame.to_local(unit_vector(direction));
+        if wo.z() <= 0.0 || wi.z() == 0.0 {
+            return 0.0;
+        }
+
+        self.pdf_local(rec, wo, wi)
+    }
+}
+
+/// Generalized Trowbridge-Reitz distribution with exponent 1, which has the
+/// long tails Burley fit to clearcoat highlights
+fn gtr1(cos_theta: f64, alpha: f64) -> f64 {
+    let alpha2 = alpha * alpha;
+    (alpha2 - 1.0) / (PI * alpha2.ln() * (1.0 + (alpha2 - 1.0) * cos_theta * cos_theta))
+}
+
+/// Microfacet normal with density [gtr1] times its cosine
+fn sample_gtr1(alpha: f64) -> Vec3 {
+    let alpha2 = alpha * alpha;
+    let cos_theta = ((1.0 - alpha2.powf(1.0 - random_f64())) / (1.0 - alpha2)).max(0.0).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * random_f64();
+
+    Vec3::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+}
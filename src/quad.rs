@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::{is_cut_out, Material};
+use crate::ray::Ray;
+use crate::rt_weekend::random_f64;
+use crate::vec3::{cross, dot, unit_vector, Point3, Vec3};
+
+/// Parallelogram with a corner at [q] and sides [u] and [v]
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    /// turns a point on the plane into its [u], [v] coordinates
+    w: Vec3,
+    mat: Arc<dyn Material>,
+    normal: Vec3,
+    d: f64,
+    area: f64,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
+        let n = cross(u, v);
+        let normal = unit_vector(n);
+
+        Self {
+            q,
+            u,
+            v,
+            w: n / dot(n, n),
+            mat,
+            normal,
+            d: dot(normal, q),
+            area: n.length(),
+        }
+    }
+
+    /// Whether the plane coordinates [alpha], [beta] fall inside the quad
+    fn is_interior(alpha: f64, beta: f64) -> bool {
+        let unit_interval = Interval::new(0.0, 1.0);
+
+        unit_interval.contains(alpha) && unit_interval.contains(beta)
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let denom = dot(self.normal, *r.direction());
+
+        // no hit if the ray is parallel to the plane
+        if denom.abs() < 1e-8 {
+            return false;
+        }
+
+        let t = (self.d - dot(self.normal, *r.origin())) / denom;
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        let intersection = r.at(t);
+        let planar_hitpt_vector = intersection - self.q;
+        let alpha = dot(self.w, cross(planar_hitpt_vector, self.v));
+        let beta = dot(self.w, cross(self.u, planar_hitpt_vector));
+
+        if !Quad::is_interior(alpha, beta) || is_cut_out(self.mat.as_ref(), r, alpha, beta, &intersection) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = intersection;
+        (rec.u, rec.v) = (alpha, beta);
+        (rec.dpdu, rec.dpdv) = (self.u, self.v);
+        rec.mat = self.mat.clone();
+        rec.set_face_normal(r, &self.normal);
+
+        true
+    }
+
+    /// Uniform over the area of the quad, as a solid angle density from [origin]
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let mut rec = HitRecord::default();
+        if !self.hit(&Ray::new(origin, direction), Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = (dot(direction, rec.normal) / direction.length()).abs();
+
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        let p = self.q + (random_f64() * self.u) + (random_f64() * self.v);
+        p - origin
+    }
+}
@@ -1,25 +1,37 @@
+use crate::spectrum::Wavelengths;
 use crate::vec3::{Point3, Vec3};
 
 pub struct Ray {
     orig: Point3,
     dir: Vec3,
     tm: f64,
+    /// set in spectral mode only
+    wavelengths: Option<Wavelengths>,
+    /// in RGB mode, the one color channel a path still carries, once
+    /// something sent the others elsewhere
+    channel: Option<usize>,
 }
 
-impl Ray {
-    pub fn default() -> Self {
+impl Default for Ray {
+    fn default() -> Self {
         Self {
             orig: Point3::zero(),
             dir: Vec3::zero(),
             tm: 0.0,
+            wavelengths: None,
+            channel: None,
         }
     }
+}
 
+impl Ray {
     pub fn new(origin: Point3, direction: Vec3) -> Self {
         Self {
             orig: origin,
             dir: direction,
             tm: 0.0,
+            wavelengths: None,
+            channel: None,
         }
     }
 
@@ -28,15 +40,31 @@ impl Ray {
             orig: origin,
             dir: direction,
             tm: time,
+            wavelengths: None,
+            channel: None,
         }
     }
 
+    pub fn with_wavelengths(mut self, wavelengths: Option<Wavelengths>) -> Self {
+        self.wavelengths = wavelengths;
+        self
+    }
+
+    pub fn with_channel(mut self, channel: Option<usize>) -> Self {
+        self.channel = channel;
+        self
+    }
+
     pub fn origin(&self) -> &Point3 { &self.orig }
 
     pub fn direction(&self) -> &Vec3 { &self.dir }
 
     pub fn time(&self) -> f64 { self.tm }
 
+    pub fn wavelengths(&self) -> Option<Wavelengths> { self.wavelengths }
+
+    pub fn channel(&self) -> Option<usize> { self.channel }
+
     pub fn at(&self, t: f64) -> Point3 {
         self.orig + t * self.dir
     }
@@ -1,7 +1,6 @@
+use std::f64::consts::PI;
 use rand::Rng;
 
-const PI: f64 = 3.1415926535897932385_f64;
-
 pub fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * PI / 180.0
 }
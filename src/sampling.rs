@@ -0,0 +1,103 @@
+/// Piecewise-constant distribution over [0,1) built from non-negative
+/// weights, one per equally sized bucket
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    func_int: f64,
+}
+
+impl Distribution1D {
+    pub fn new(func: Vec<f64>) -> Self {
+        let n = func.len();
+        let mut cdf = vec![0.0; n + 1];
+
+        for i in 1..=n {
+            cdf[i] = cdf[i - 1] + func[i - 1] / (n as f64);
+        }
+
+        let func_int = cdf[n];
+
+        // all weights zero: fall back to a uniform distribution
+        for (i, c) in cdf.iter_mut().enumerate().skip(1) {
+            *c = if func_int > 0.0 { *c / func_int } else { (i as f64) / (n as f64) };
+        }
+
+        Self { func, cdf, func_int }
+    }
+
+    pub fn count(&self) -> usize { self.func.len() }
+
+    pub fn integral(&self) -> f64 { self.func_int }
+
+    /// Maps a uniform sample `u` in [0,1) to the distribution.
+    /// Returns the sample in [0,1), its density and the bucket it fell in
+    pub fn sample(&self, u: f64) -> (f64, f64, usize) {
+        let n = self.count();
+
+        // last bucket whose cdf does not exceed u
+        let offset = self.cdf.partition_point(|&c| c <= u).saturating_sub(1).min(n - 1);
+
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0.0 {
+            du /= width;
+        }
+
+        ((offset as f64 + du) / (n as f64), self.bucket_pdf(offset), offset)
+    }
+
+    /// Density of the distribution at `x` in [0,1)
+    pub fn pdf(&self, x: f64) -> f64 {
+        let offset = ((x * self.count() as f64) as usize).min(self.count() - 1);
+        self.bucket_pdf(offset)
+    }
+
+    fn bucket_pdf(&self, offset: usize) -> f64 {
+        if self.func_int > 0.0 { self.func[offset] / self.func_int } else { 1.0 }
+    }
+}
+
+/// Piecewise-constant distribution over [0,1)², e.g. over the texels of
+/// an image. Rows are sampled from the marginal, then a column from the
+/// row's conditional distribution
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// `func` holds `nu * nv` weights in row-major order
+    pub fn new(func: &[f64], nu: usize, nv: usize) -> Self {
+        let conditional: Vec<Distribution1D> = func
+            .chunks(nu)
+            .take(nv)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|d| d.integral()).collect());
+
+        Self { conditional, marginal }
+    }
+
+    /// Returns a sample (u, v) in [0,1)² with its density
+    pub fn sample(&self, u0: f64, u1: f64) -> ((f64, f64), f64) {
+        let (v, pdf_v, row) = self.marginal.sample(u1);
+        let (u, pdf_u, _) = self.conditional[row].sample(u0);
+
+        ((u, v), pdf_u * pdf_v)
+    }
+
+    /// Density of the distribution at (u, v)
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let row = ((v * self.marginal.count() as f64) as usize).min(self.marginal.count() - 1);
+        self.conditional[row].pdf(u) * self.marginal.pdf(v)
+    }
+}
+
+/// Multiple importance sampling weight for a sample drawn with density
+/// `f_pdf`, when it could also have been drawn with density `g_pdf`
+pub fn power_heuristic(f_pdf: f64, g_pdf: f64) -> f64 {
+    let f = f_pdf * f_pdf;
+    let g = g_pdf * g_pdf;
+
+    if f + g > 0.0 { f / (f + g) } else { 0.0 }
+}
@@ -0,0 +1,285 @@
+use std::f64::consts::PI;
+use crate::background::Background;
+use crate::color::Color;
+use crate::onb::Onb;
+use crate::rt_weekend::{degrees_to_radians, random_f64};
+use crate::vec3::{dot, unit_vector, Vec3};
+
+/// Apparent angular radius of the sun seen from earth, in degrees
+const SUN_ANGULAR_RADIUS: f64 = 0.2667;
+/// Illuminance of the sun before the atmosphere attenuates it, in klux
+const SUN_ILLUMINANCE: f64 = 120.0;
+/// Share of background samples spent on the sun disk while it is up
+const SUN_SAMPLE_PROBABILITY: f64 = 0.5;
+
+/// Analytic daylight sky after Preetham, Shirley and Smits,
+/// "A Practical Analytic Model for Daylight" (1999), plus a sun disk
+/// of finite size that next-event estimation samples directly.
+/// Luminances are in kcd/m², scaled by [intensity]
+pub struct Sky {
+    sun_direction: Vec3,
+    /// sun's zenith angle, clamped to the horizon
+    theta_sun: f64,
+    turbidity: f64,
+    zenith: Color, // zenith x, y chromaticity and Y luminance
+    perez_x: [f64; 5],
+    perez_y: [f64; 5],
+    perez_lum: [f64; 5],
+    cos_sun_radius: f64,
+    sun_radiance: Color,
+
+    /// scale factor applied to sky and sun radiance
+    pub intensity: f64,
+    /// albedo of the ground that reflects the sky below the horizon
+    pub ground_albedo: Color,
+}
+
+impl Sky {
+    /// [sun_elevation] is the sun's angle above the horizon, and [sun_azimuth]
+    /// its angle clockwise from -z when looking down, both in degrees.
+    /// [turbidity] ranges from 2 (clear) to about 10 (hazy)
+    pub fn new(sun_elevation: f64, sun_azimuth: f64, turbidity: f64) -> Self {
+        let elevation = degrees_to_radians(sun_elevation);
+        let azimuth = degrees_to_radians(sun_azimuth);
+        let sun_direction = Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+
+        // the model doesn't hold for a sun below the horizon
+        let theta_sun = (PI / 2.0 - elevation).clamp(0.0, PI / 2.0 - 1e-3);
+        let t = turbidity;
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_sun);
+        let zenith_lum = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith_x = zenith_chromaticity(t, theta_sun, &[
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_y = zenith_chromaticity(t, theta_sun, &[
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let mut sky = Self {
+            sun_direction,
+            theta_sun,
+            turbidity,
+            zenith: Color::new(zenith_x, zenith_y, zenith_lum.max(0.0)),
+            perez_x: [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            perez_y: [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+            perez_lum: [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            cos_sun_radius: 1.0,
+            sun_radiance: Color::zero(),
+            intensity: 0.025,
+            ground_albedo: Color::new(0.3, 0.3, 0.3),
+        };
+
+        sky.set_sun_radius(SUN_ANGULAR_RADIUS);
+        sky
+    }
+
+    /// Resizes the sun disk to [degrees] of angular radius, keeping the
+    /// light it casts the same: a larger sun only softens shadows
+    pub fn with_sun_radius(mut self, degrees: f64) -> Self {
+        self.set_sun_radius(degrees);
+        self
+    }
+
+    fn set_sun_radius(&mut self, degrees: f64) {
+        self.cos_sun_radius = degrees_to_radians(degrees.max(1e-3)).cos();
+
+        if self.sun_direction.y() <= 0.0 {
+            self.sun_radiance = Color::zero();
+            return;
+        }
+
+        let solid_angle = 2.0 * PI * (1.0 - self.cos_sun_radius);
+        self.sun_radiance = (SUN_ILLUMINANCE / solid_angle) * sun_transmittance(self.theta_sun, self.turbidity);
+    }
+
+    fn perez(coefficients: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+        let [a, b, c, d, e] = *coefficients;
+        let cos_gamma = gamma.cos();
+
+        (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+    }
+
+    /// Sky radiance for a unit direction above the horizon, without the sun
+    fn sky_radiance(&self, direction: Vec3) -> Color {
+        let cos_theta = direction.y().max(0.01);
+        let gamma = dot(direction, self.sun_direction).clamp(-1.0, 1.0).acos();
+
+        let channel = |coefficients: &[f64; 5], zenith: f64| {
+            zenith * Self::perez(coefficients, cos_theta, gamma) / Self::perez(coefficients, 1.0, self.theta_sun)
+        };
+
+        let x = channel(&self.perez_x, self.zenith.x());
+        let y = channel(&self.perez_y, self.zenith.y());
+        let lum = channel(&self.perez_lum, self.zenith.z());
+
+        xyy_to_rgb(x, y, lum)
+    }
+
+    fn sun_visible(&self, direction: Vec3) -> bool {
+        dot(direction, self.sun_direction) >= self.cos_sun_radius
+    }
+
+    fn sun_sample_probability(&self) -> f64 {
+        if self.sun_radiance.near_zero() { 0.0 } else { SUN_SAMPLE_PROBABILITY }
+    }
+}
+
+impl Background for Sky {
+    fn value(&self, direction: Vec3) -> Color {
+        let unit_direction = unit_vector(direction);
+
+        if unit_direction.y() < 0.0 {
+            // diffuse ground lit by the sky overhead
+            let mirrored = Vec3::new(unit_direction.x(), -unit_direction.y(), unit_direction.z());
+            return self.intensity * self.ground_albedo * self.sky_radiance(mirrored);
+        }
+
+        let mut radiance = self.sky_radiance(unit_direction);
+        if self.sun_visible(unit_direction) {
+            radiance += self.sun_radiance;
+        }
+
+        self.intensity * radiance
+    }
+
+    /// Samples the sun cone, or otherwise the upper hemisphere uniformly
+    fn sample(&self) -> Option<(Vec3, f64)> {
+        let direction = if random_f64() < self.sun_sample_probability() {
+            let z = 1.0 + random_f64() * (self.cos_sun_radius - 1.0);
+            Onb::new(self.sun_direction).transform(spherical_direction(z, 2.0 * PI * random_f64()))
+        } else {
+            let z = random_f64();
+            let local = spherical_direction(z, 2.0 * PI * random_f64());
+            Vec3::new(local.x(), local.z(), local.y())
+        };
+
+        let pdf = self.pdf(direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+
+        Some((direction, pdf))
+    }
+
+    fn pdf(&self, direction: Vec3) -> f64 {
+        let unit_direction = unit_vector(direction);
+        let sun_probability = self.sun_sample_probability();
+
+        let mut pdf = 0.0;
+        if unit_direction.y() > 0.0 {
+            pdf += (1.0 - sun_probability) / (2.0 * PI);
+        }
+        if sun_probability > 0.0 && self.sun_visible(unit_direction) {
+            pdf += sun_probability / (2.0 * PI * (1.0 - self.cos_sun_radius));
+        }
+
+        pdf
+    }
+}
+
+/// Unit vector at polar cosine [z] and azimuth [phi] around the local z axis
+fn spherical_direction(z: f64, phi: f64) -> Vec3 {
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    Vec3::new(phi.cos() * r, phi.sin() * r, z)
+}
+
+fn zenith_chromaticity(turbidity: f64, theta_sun: f64, m: &[[f64; 4]; 3]) -> f64 {
+    let t = [turbidity * turbidity, turbidity, 1.0];
+    let s = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+
+    (0..3).map(|i| t[i] * (0..4).map(|j| m[i][j] * s[j]).sum::<f64>()).sum()
+}
+
+/// Fraction of sunlight that makes it through Rayleigh and aerosol
+/// scattering, for the red, green and blue wavelengths
+fn sun_transmittance(theta_sun: f64, turbidity: f64) -> Color {
+    let theta_degrees = theta_sun.to_degrees();
+    let relative_optical_mass = 1.0 / (theta_sun.cos() + 0.15 * (93.885 - theta_degrees).powf(-1.253));
+    let beta = 0.04608365822050 * turbidity - 0.04586025928522;
+    let alpha = 1.3;
+
+    let channel = |lambda_um: f64| {
+        let rayleigh = (-0.008735 * lambda_um.powf(-4.08) * relative_optical_mass).exp();
+        let aerosol = (-beta * lambda_um.powf(-alpha) * relative_optical_mass).exp();
+        rayleigh * aerosol
+    };
+
+    Color::new(channel(0.680), channel(0.550), channel(0.440))
+}
+
+/// CIE xyY to linear sRGB
+fn xyy_to_rgb(x: f64, y: f64, lum: f64) -> Color {
+    if y <= 0.0 {
+        return Color::zero();
+    }
+
+    let cx = x / y * lum;
+    let cz = (1.0 - x - y) / y * lum;
+
+    Color::new(
+        (3.2406 * cx - 1.5372 * lum - 0.4986 * cz).max(0.0),
+        (-0.9689 * cx + 1.8758 * lum + 0.0415 * cz).max(0.0),
+        (0.0557 * cx - 0.2040 * lum + 1.0570 * cz).max(0.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Radiance the sun disk adds toward [direction]
+    fn sun_disk(sky: &Sky, direction: Vec3) -> Color {
+        let mut clear = Sky::new(40.0, 120.0, 3.0);
+        clear.sun_radiance = Color::zero();
+        sky.value(direction) - clear.value(direction)
+    }
+
+    #[test]
+    fn a_larger_sun_casts_the_same_light() {
+        let sky = Sky::new(40.0, 120.0, 3.0);
+        let soft = Sky::new(40.0, 120.0, 3.0).with_sun_radius(3.0);
+
+        let power = |sky: &Sky| sky.sun_radiance.y() * 2.0 * PI * (1.0 - sky.cos_sun_radius);
+        assert!((power(&sky) - power(&soft)).abs() < 1e-9 * power(&sky));
+        assert!(soft.sun_radiance.y() < sky.sun_radiance.y());
+    }
+
+    #[test]
+    fn a_larger_sun_covers_more_of_the_sky() {
+        let sky = Sky::new(40.0, 120.0, 3.0);
+        let soft = Sky::new(40.0, 120.0, 3.0).with_sun_radius(3.0);
+
+        // two degrees off the center of the sun
+        let off_center = Onb::new(sky.sun_direction).transform(spherical_direction(degrees_to_radians(2.0).cos(), 0.0));
+        assert_eq!(sun_disk(&sky, off_center).y(), 0.0);
+        assert!(sun_disk(&soft, off_center).y() > 0.0);
+        assert!(sun_disk(&sky, sky.sun_direction).y() > sun_disk(&soft, sky.sun_direction).y());
+    }
+}
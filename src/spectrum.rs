@@ -0,0 +1,267 @@
+use std::sync::OnceLock;
+use crate::color::Color;
+use crate::ray::Ray;
+use crate::rt_weekend::random_f64;
+
+/// Range of wavelengths spectral mode samples, in nanometers
+pub const LAMBDA_MIN: f64 = 380.0;
+pub const LAMBDA_MAX: f64 = 780.0;
+
+/// Wavelengths in nanometers standing in for red, green and blue
+pub const RGB_WAVELENGTHS: [f64; 3] = [630.0, 532.0, 465.0];
+
+/// Wavelengths a path carries in spectral mode, in nanometers: a hero
+/// wavelength picked at random, and two more spaced evenly around the
+/// range from it (Wilkie et al. 2014). The components of the path's colors
+/// then hold spectral values at these wavelengths, in the same order
+#[derive(Copy, Clone)]
+pub struct Wavelengths {
+    lambda: [f64; 3],
+    /// only the hero is left, after something sent the others elsewhere
+    secondary_terminated: bool,
+}
+
+impl Wavelengths {
+    pub fn sample() -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let hero = random_f64() * range;
+        let lambda = [0.0, 1.0, 2.0].map(|i| LAMBDA_MIN + (hero + i * range / 3.0) % range);
+
+        Self { lambda, secondary_terminated: false }
+    }
+
+    pub fn hero(&self) -> f64 {
+        self.lambda[0]
+    }
+
+    /// Color holding [f] of each wavelength
+    pub fn evaluate(&self, f: impl Fn(f64) -> f64) -> Color {
+        Color::new(f(self.lambda[0]), f(self.lambda[1]), f(self.lambda[2]))
+    }
+
+    /// Spectrum resembling the RGB color [c], sampled at these wavelengths
+    pub fn upsample(&self, c: Color) -> Color {
+        self.evaluate(|lambda| rgb_to_spectrum(c, lambda))
+    }
+
+    /// For effects like dispersion that send each wavelength its own way:
+    /// keeps the hero only, and returns the weight the path's throughput
+    /// takes on to make up for the others
+    pub fn terminate_secondary(&mut self) -> Color {
+        if self.secondary_terminated {
+            return Color::new(1.0, 0.0, 0.0);
+        }
+
+        self.secondary_terminated = true;
+        Color::new(3.0, 0.0, 0.0)
+    }
+
+    /// Linear RGB for the [radiance] a path brought back at these wavelengths
+    pub fn to_rgb(self, radiance: Color) -> Color {
+        // the uniform density of the wavelengths leaves their average times the range
+        let mut xyz = Color::zero();
+        for i in 0..3 {
+            xyz += radiance[i] * cie_xyz(self.lambda[i]);
+        }
+        let rgb = xyz_to_rgb(((LAMBDA_MAX - LAMBDA_MIN) / 3.0) * xyz);
+
+        // white balance, so an RGB white upsampled and back stays white
+        let white = equal_energy_white();
+        Color::new(rgb.x() / white.x(), rgb.y() / white.y(), rgb.z() / white.z())
+    }
+}
+
+/// The one wavelength a path goes on with, once something like dispersion
+/// sends each its own way: the hero in spectral mode, or one color channel
+/// in RGB mode
+pub struct SingleWavelength {
+    pub lambda: f64,
+    /// weight the path's throughput takes on, to make up for the others
+    pub weight: Color,
+    wavelengths: Option<Wavelengths>,
+    channel: Option<usize>,
+}
+
+impl SingleWavelength {
+    /// The one the path along [r] kept already, or else a new pick
+    pub fn along(r: &Ray) -> Self {
+        if let Some(mut wavelengths) = r.wavelengths() {
+            let weight = wavelengths.terminate_secondary();
+            return Self { lambda: wavelengths.hero(), weight, wavelengths: Some(wavelengths), channel: None };
+        }
+
+        let (channel, scale) = match r.channel() {
+            Some(channel) => (channel, 1.0),
+            None => (((3.0 * random_f64()) as usize).min(2), 3.0),
+        };
+        let mut weight = Color::zero();
+        weight[channel] = scale;
+
+        Self { lambda: RGB_WAVELENGTHS[channel], weight, wavelengths: None, channel: Some(channel) }
+    }
+
+    /// [r] going on with this wavelength only
+    pub fn carry(&self, r: Ray) -> Ray {
+        r.with_wavelengths(self.wavelengths).with_channel(self.channel)
+    }
+}
+
+/// [c] as seen along [r]: unchanged in RGB mode, and upsampled to the
+/// ray's wavelengths in spectral mode
+pub fn along_ray(r: &Ray, c: Color) -> Color {
+    match r.wavelengths() {
+        Some(wavelengths) => wavelengths.upsample(c),
+        None => c,
+    }
+}
+
+/// Wavelengths the components of colors along [r] stand for: the ray's
+/// own in spectral mode, or [RGB_WAVELENGTHS]
+pub fn wavelengths_along(r: &Ray) -> [f64; 3] {
+    match r.wavelengths() {
+        Some(wavelengths) => wavelengths.lambda,
+        None => RGB_WAVELENGTHS,
+    }
+}
+
+/// Color along [r] holding [f] of each of its [wavelengths_along]
+pub fn evaluate_along(r: &Ray, f: impl Fn(f64) -> f64) -> Color {
+    let [l0, l1, l2] = wavelengths_along(r);
+    Color::new(f(l0), f(l1), f(l2))
+}
+
+/// Value at [lambda] of a quantity given per color channel, like an index
+/// of refraction, interpolated between [RGB_WAVELENGTHS]. Exact at those
+pub fn rgb_at(c: Color, lambda: f64) -> f64 {
+    let [red, green, blue] = RGB_WAVELENGTHS;
+    if lambda >= red {
+        c.x()
+    } else if lambda >= green {
+        c.y() + (c.x() - c.y()) * (lambda - green) / (red - green)
+    } else if lambda >= blue {
+        c.z() + (c.y() - c.z()) * (lambda - blue) / (green - blue)
+    } else {
+        c.z()
+    }
+}
+
+/// RGB of a spectrum that is one at every wavelength
+fn equal_energy_white() -> Color {
+    static WHITE: OnceLock<Color> = OnceLock::new();
+
+    *WHITE.get_or_init(|| {
+        let steps = 400;
+        let step = (LAMBDA_MAX - LAMBDA_MIN) / steps as f64;
+        let mut xyz = Color::zero();
+        for i in 0..steps {
+            xyz += step * cie_xyz(LAMBDA_MIN + (i as f64 + 0.5) * step);
+        }
+        xyz_to_rgb(xyz)
+    })
+}
+
+/// CIE 1931 color matching functions, from the multi-lobe fit of
+/// Wyman, Sloan and Shirley, "Simple Analytic Approximations to the CIE XYZ
+/// Color Matching Functions" (2013)
+pub fn cie_xyz(lambda: f64) -> Color {
+    let g = |mu: f64, sigma_below: f64, sigma_above: f64| {
+        let t = (lambda - mu) / if lambda < mu { sigma_below } else { sigma_above };
+        (-0.5 * t * t).exp()
+    };
+
+    Color::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+/// CIE XYZ to linear sRGB
+pub fn xyz_to_rgb(xyz: Color) -> Color {
+    Color::new(
+        3.2406 * xyz.x() - 1.5372 * xyz.y() - 0.4986 * xyz.z(),
+        -0.9689 * xyz.x() + 1.8758 * xyz.y() + 0.0415 * xyz.z(),
+        0.0557 * xyz.x() - 0.2040 * xyz.y() + 1.0570 * xyz.z(),
+    )
+}
+
+// Smits, "An RGB to Spectrum Conversion for Reflectances" (1999):
+// smooth spectra for white and the primary and secondary colors,
+// in ten bins from 380 to 720nm
+const SMITS_WHITE: [f64; 10] = [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+const SMITS_CYAN: [f64; 10] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+const SMITS_MAGENTA: [f64; 10] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+const SMITS_YELLOW: [f64; 10] = [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+const SMITS_RED: [f64; 10] = [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+const SMITS_GREEN: [f64; 10] = [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+const SMITS_BLUE: [f64; 10] = [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+
+/// Value at [lambda] of a spectrum given in Smits' bins, interpolated
+/// between bin centers
+fn smits_bin(spectrum: &[f64; 10], lambda: f64) -> f64 {
+    let x = ((lambda - 380.0) / 34.0 - 0.5).clamp(0.0, 9.0);
+    let i = (x as usize).min(8);
+    let t = x - i as f64;
+
+    (1.0 - t) * spectrum[i] + t * spectrum[i + 1]
+}
+
+/// Smits' upsampling of the RGB color [c], evaluated at [lambda]: white,
+/// plus the secondary and then the primary color that make up the rest
+pub fn rgb_to_spectrum(c: Color, lambda: f64) -> f64 {
+    let (r, g, b) = (c.x(), c.y(), c.z());
+    let at = |spectrum: &[f64; 10]| smits_bin(spectrum, lambda);
+
+    if r <= g && r <= b {
+        r * at(&SMITS_WHITE) + if g <= b {
+            (g - r) * at(&SMITS_CYAN) + (b - g) * at(&SMITS_BLUE)
+        } else {
+            (b - r) * at(&SMITS_CYAN) + (g - b) * at(&SMITS_GREEN)
+        }
+    } else if g <= r && g <= b {
+        g * at(&SMITS_WHITE) + if r <= b {
+            (r - g) * at(&SMITS_MAGENTA) + (b - r) * at(&SMITS_BLUE)
+        } else {
+            (b - g) * at(&SMITS_MAGENTA) + (r - b) * at(&SMITS_RED)
+        }
+    } else {
+        b * at(&SMITS_WHITE) + if r <= g {
+            (r - b) * at(&SMITS_YELLOW) + (g - r) * at(&SMITS_GREEN)
+        } else {
+            (g - b) * at(&SMITS_YELLOW) + (r - g) * at(&SMITS_RED)
+        }
+    }
+}
+
+/// Spectral quantity like an index of refraction, tabulated at increasing
+/// wavelengths and interpolated linearly between them
+pub struct SampledSpectrum {
+    samples: Vec<(f64, f64)>,
+}
+
+impl SampledSpectrum {
+    /// [samples] are (wavelength in nanometers, value) pairs
+    pub fn new(mut samples: Vec<(f64, f64)>) -> Self {
+        samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { samples }
+    }
+
+    pub fn value(&self, lambda: f64) -> f64 {
+        let samples = &self.samples;
+        if samples.is_empty() {
+            return 0.0;
+        }
+
+        let i = samples.partition_point(|&(l, _)| l < lambda);
+        if i == 0 {
+            return samples[0].1;
+        }
+        if i == samples.len() {
+            return samples[i - 1].1;
+        }
+
+        let (l0, v0) = samples[i - 1];
+        let (l1, v1) = samples[i];
+        v0 + (v1 - v0) * (lambda - l0) / (l1 - l0)
+    }
+}
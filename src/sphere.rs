@@ -1,9 +1,10 @@
-use crate::color::Color;
 use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
-use crate::material::{Lambertian, Material};
+use crate::material::{is_cut_out, Material};
 use crate::ray::Ray;
-use crate::vec3::{dot, Point3, Vec3};
+use crate::onb::Onb;
+use crate::vec3::{dot, random_to_sphere, random_unit_vector, Point3, Vec3};
+use std::f64::consts::PI;
 use std::sync::Arc;
 
 pub struct Sphere {
@@ -28,6 +29,26 @@ impl Sphere {
             mat,
         }
     }
+
+    /// Surface coordinates of [p], a point on the unit sphere around the
+    /// origin: [u] goes around the y axis from x = -1, [v] from y = -1 to y = +1
+    fn get_sphere_uv(p: Point3) -> (f64, f64) {
+        let theta = (-p.y()).acos();
+        let phi = (-p.z()).atan2(p.x()) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    /// Tangents along [u] and [v] of [get_sphere_uv] at [p], a point on the
+    /// unit sphere around the origin, for a sphere of [radius]
+    fn get_sphere_tangents(p: Point3, radius: f64) -> (Vec3, Vec3) {
+        let sin_theta = (p.x() * p.x() + p.z() * p.z()).sqrt().max(1e-8);
+
+        let dpdu = 2.0 * PI * radius * Vec3::new(p.z(), 0.0, -p.x());
+        let dpdv = PI * radius * Vec3::new(-p.x() * p.y() / sin_theta, sin_theta, -p.y() * p.z() / sin_theta);
+
+        (dpdu, dpdv)
+    }
 }
 
 impl Hittable for Sphere {
@@ -47,24 +68,60 @@ impl Hittable for Sphere {
 
         let sqrt_d = discriminant.sqrt();
 
-        // nearest root in acceptable range
-        let mut root = (h - sqrt_d) / a;
-        if !ray_t.surrounds(root) {
-            root = (h + sqrt_d) / a;
-
+        // nearest root in acceptable range, where the surface isn't cut out
+        for root in [(h - sqrt_d) / a, (h + sqrt_d) / a] {
             if !ray_t.surrounds(root) {
-                return false;
+                continue;
             }
+
+            let p = r.at(root);
+            let outward_normal = (p - current_center) / self.radius;
+            let (u, v) = Sphere::get_sphere_uv(outward_normal);
+            if is_cut_out(self.mat.as_ref(), r, u, v, &p) {
+                continue;
+            }
+
+            rec.t = root;
+            rec.p = p;
+            rec.set_face_normal(r, &outward_normal);
+            (rec.u, rec.v) = (u, v);
+            (rec.dpdu, rec.dpdv) = Sphere::get_sphere_tangents(outward_normal, self.radius);
+
+            rec.mat = self.mat.clone();
+
+            return true;
         }
 
-        rec.t = root;
-        rec.p = r.at(rec.t);
+        false
+    }
+
+    /// Uniform over the cone of directions the sphere covers from [origin]
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        // only works for stationary spheres
+        let mut rec = HitRecord::default();
+        if !self.hit(&Ray::new(origin, direction), Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
 
-        let outward_normal = (rec.p - current_center) / self.radius;
-        rec.set_face_normal(r, &outward_normal);
+        let distance_squared = (self.center.at(0.0) - origin).length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return 1.0 / (4.0 * PI);
+        }
 
-        rec.mat = self.mat.clone();
+        let cos_theta_max = (1.0 - self.radius * self.radius / distance_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        let direction = self.center.at(0.0) - origin;
+        let distance_squared = direction.length_squared();
+
+        if distance_squared <= self.radius * self.radius {
+            return random_unit_vector();
+        }
 
-        true
+        Onb::new(direction).transform(random_to_sphere(self.radius, distance_squared))
     }
 }
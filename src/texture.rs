@@ -0,0 +1,126 @@
+use std::sync::Arc;
+use image::ImageError;
+use crate::color::Color;
+use crate::vec3::Point3;
+
+/// Color that varies over a surface, looked up by its [u], [v]
+/// coordinates or by the point [p] itself
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+}
+
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> Self {
+        Self { albedo }
+    }
+
+    pub fn from_rgb(red: f64, green: f64, blue: f64) -> Self {
+        Self::new(Color::new(red, green, blue))
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.albedo
+    }
+}
+
+/// 3D checker pattern of cubes [scale] units across
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self { inv_scale: 1.0 / scale, even, odd }
+    }
+
+    pub fn from_colors(scale: f64, c1: Color, c2: Color) -> Self {
+        Self::new(scale, Arc::new(SolidColor::new(c1)), Arc::new(SolidColor::new(c2)))
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let x = (self.inv_scale * p.x()).floor() as i64;
+        let y = (self.inv_scale * p.y()).floor() as i64;
+        let z = (self.inv_scale * p.z()).floor() as i64;
+
+        if (x + y + z) % 2 == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// Image wrapped around a surface by its [u], [v] coordinates, repeating
+/// outside [0,1]. The top row of the image is at v = 1
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl ImageTexture {
+    /// Loads a color image, undoing the gamma of the file
+    pub fn load(path: &str) -> Result<Self, ImageError> {
+        let texture = Self::load_data(path)?;
+        // inverse of the gamma 2 that write_color applies
+        let pixels = texture.pixels.iter().map(|c| *c * *c).collect();
+
+        Ok(Self { pixels, ..texture })
+    }
+
+    /// Loads an image of values that aren't colors, like a normal or height
+    /// map, as they are in the file
+    pub fn load_data(path: &str) -> Result<Self, ImageError> {
+        let image = image::open(path)?.into_rgb32f();
+
+        let pixels = image
+            .pixels()
+            .map(|p| Color::new(p[0] as f64, p[1] as f64, p[2] as f64))
+            .collect();
+
+        Ok(Self::from_pixels(image.width() as usize, image.height() as usize, pixels))
+    }
+
+    /// Loads the alpha channel of an image, as gray, for opacity masks
+    pub fn load_alpha(path: &str) -> Result<Self, ImageError> {
+        let image = image::open(path)?.into_rgba32f();
+
+        let pixels = image
+            .pixels()
+            .map(|p| Color::new(p[3] as f64, p[3] as f64, p[3] as f64))
+            .collect();
+
+        Ok(Self::from_pixels(image.width() as usize, image.height() as usize, pixels))
+    }
+
+    /// `pixels` are row-major, starting at the top left
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        Self { width, height, pixels }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        if self.pixels.is_empty() {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+
+        let u = u.rem_euclid(1.0);
+        let v = 1.0 - v.rem_euclid(1.0);
+
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+
+        self.pixels[j * self.width + i]
+    }
+}
@@ -0,0 +1,34 @@
+use std::sync::Arc;
+use crate::color::luminance;
+use crate::fresnel::{fr_thin_film, Complex};
+use crate::hittable::HitRecord;
+use crate::texture::{SolidColor, Texture};
+
+/// Transparent film on a surface, like soap, oil or a lens coating,
+/// thin enough for reflections off its two sides to interfere
+pub struct ThinFilm {
+    thickness: Arc<dyn Texture>,
+    max_thickness: f64,
+    refraction_index: f64,
+}
+
+impl ThinFilm {
+    /// [thickness] in nanometers
+    pub fn new(thickness: f64, refraction_index: f64) -> Self {
+        Self::textured(Arc::new(SolidColor::from_rgb(1.0, 1.0, 1.0)), thickness, refraction_index)
+    }
+
+    /// Film whose thickness varies over the surface, from zero where
+    /// [thickness] is black to [max_thickness] nanometers where it's white
+    pub fn textured(thickness: Arc<dyn Texture>, max_thickness: f64, refraction_index: f64) -> Self {
+        Self { thickness, max_thickness, refraction_index }
+    }
+
+    /// Reflectance at [rec] for light of [wavelength] arriving at
+    /// [cos_theta_i] from a medium of index [eta_i], over a substrate of
+    /// complex index [eta_t]
+    pub fn reflectance(&self, rec: &HitRecord, cos_theta_i: f64, eta_i: f64, eta_t: Complex, wavelength: f64) -> f64 {
+        let thickness = self.max_thickness * luminance(self.thickness.value(rec.u, rec.v, &rec.p)).max(0.0);
+        fr_thin_film(cos_theta_i, eta_i, self.refraction_index, eta_t, thickness, wavelength)
+    }
+}
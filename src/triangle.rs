@@ -0,0 +1,137 @@
+use std::sync::Arc;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::{is_cut_out, Material};
+use crate::ray::Ray;
+use crate::rt_weekend::random_f64;
+use crate::vec3::{cross, dot, unit_vector, Point3, Vec3};
+
+/// Triangle with corners [a], [b] and [c], the building block of meshes
+pub struct Triangle {
+    a: Point3,
+    b: Point3,
+    c: Point3,
+    /// texture coordinates at each corner
+    uvs: [(f64, f64); 3],
+    mat: Arc<dyn Material>,
+    normal: Vec3,
+    area: f64,
+}
+
+impl Triangle {
+    /// Surface coordinates run from [a] toward [b] for u, and toward [c] for v
+    pub fn new(a: Point3, b: Point3, c: Point3, mat: Arc<dyn Material>) -> Self {
+        let n = cross(b - a, c - a);
+
+        Self {
+            a,
+            b,
+            c,
+            uvs: [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            mat,
+            normal: unit_vector(n),
+            area: n.length() / 2.0,
+        }
+    }
+
+    /// Texture coordinates of the corners, as meshes come with them
+    pub fn with_uvs(mut self, uvs: [(f64, f64); 3]) -> Self {
+        self.uvs = uvs;
+        self
+    }
+
+    /// How a point on the triangle moves with its texture coordinates
+    fn tangents(&self) -> (Vec3, Vec3) {
+        let [(u0, v0), (u1, v1), (u2, v2)] = self.uvs;
+        let (du02, dv02) = (u0 - u2, v0 - v2);
+        let (du12, dv12) = (u1 - u2, v1 - v2);
+        let dp02 = self.a - self.c;
+        let dp12 = self.b - self.c;
+
+        let determinant = du02 * dv12 - dv02 * du12;
+        if determinant.abs() < 1e-12 {
+            // degenerate texture coordinates: any frame on the triangle does
+            return (self.b - self.a, self.c - self.a);
+        }
+
+        let inv_det = 1.0 / determinant;
+        let dpdu = inv_det * (dv12 * dp02 - dv02 * dp12);
+        let dpdv = inv_det * (du02 * dp12 - du12 * dp02);
+
+        (dpdu, dpdv)
+    }
+}
+
+impl Hittable for Triangle {
+    /// Möller-Trumbore intersection
+    fn hit(&self, r: &Ray, ray_t: Interval, rec: &mut HitRecord) -> bool {
+        let edge1 = self.b - self.a;
+        let edge2 = self.c - self.a;
+
+        let p = cross(*r.direction(), edge2);
+        let det = dot(edge1, p);
+
+        // no hit if the ray is parallel to the triangle
+        if det.abs() < 1e-12 {
+            return false;
+        }
+        let inv_det = 1.0 / det;
+
+        let s = *r.origin() - self.a;
+        let beta = inv_det * dot(s, p);
+        if !(0.0..=1.0).contains(&beta) {
+            return false;
+        }
+
+        let q = cross(s, edge1);
+        let gamma = inv_det * dot(*r.direction(), q);
+        if gamma < 0.0 || beta + gamma > 1.0 {
+            return false;
+        }
+
+        let t = inv_det * dot(edge2, q);
+        if !ray_t.contains(t) {
+            return false;
+        }
+
+        let alpha = 1.0 - beta - gamma;
+        let [(u0, v0), (u1, v1), (u2, v2)] = self.uvs;
+        let (u, v) = (alpha * u0 + beta * u1 + gamma * u2, alpha * v0 + beta * v1 + gamma * v2);
+        let p = r.at(t);
+
+        if is_cut_out(self.mat.as_ref(), r, u, v, &p) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = p;
+        (rec.u, rec.v) = (u, v);
+        (rec.dpdu, rec.dpdv) = self.tangents();
+        rec.mat = self.mat.clone();
+        rec.set_face_normal(r, &self.normal);
+
+        true
+    }
+
+    /// Uniform over the area of the triangle, as a solid angle density from [origin]
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let mut rec = HitRecord::default();
+        if !self.hit(&Ray::new(origin, direction), Interval::new(0.001, f64::INFINITY), &mut rec) {
+            return 0.0;
+        }
+
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = (dot(direction, rec.normal) / direction.length()).abs();
+
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        let su0 = random_f64().sqrt();
+        let beta = random_f64() * su0;
+        let gamma = 1.0 - su0;
+
+        let p = self.a + beta * (self.b - self.a) + gamma * (self.c - self.a);
+        p - origin
+    }
+}
@@ -1,3 +1,4 @@
+use std::f64::consts::PI;
 use std::fmt::{Display, Formatter};
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub};
 use crate::rt_weekend::{random_f64, random_f64_within};
@@ -221,6 +222,20 @@ pub fn random_in_unit_disk() -> Vec3 {
     }
 }
 
+/// Random direction around +z toward a sphere of [radius] at [distance_squared]
+/// from the origin, uniform over the solid angle it covers
+pub fn random_to_sphere(radius: f64, distance_squared: f64) -> Vec3 {
+    let r1 = random_f64();
+    let r2 = random_f64();
+    let z = 1.0 + r2 * ((1.0 - radius * radius / distance_squared).sqrt() - 1.0);
+
+    let phi = 2.0 * PI * r1;
+    let x = phi.cos() * (1.0 - z * z).sqrt();
+    let y = phi.sin() * (1.0 - z * z).sqrt();
+
+    Vec3::new(x, y, z)
+}
+
 pub fn reflect(v: Vec3, n: Vec3) -> Vec3 {
     v - 2.0 * dot(v, n) * n
 }